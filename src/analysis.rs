@@ -1,46 +1,117 @@
 use crate::{
-    recent::get_recent_branches,
+    git::{GitSettings, NetworkError},
+    partial,
+    recent::{default_branch, select_branches, BranchSource, Selection},
     result::{MergeAnalysisResult, MergeAnalysisStatus},
+    shallow::Deepening,
     Probranchinator,
 };
-use eyre::Result;
-use git2::Repository;
-use indicatif::ProgressStyle;
+use eyre::{Context, Result};
+use git2::{ErrorClass, ErrorCode, MergeAnalysis, Oid, Repository};
+use indicatif::{ProgressBar, ProgressStyle};
 
-use std::time::Duration;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Name under which uncommitted changes are shown in results
+pub(crate) const WORKING_TREE: &str = "(working tree)";
+
+/// How many commits shallow history is deepened by at most, unless set otherwise
+pub(crate) const DEFAULT_DEEPEN_LIMIT: u32 = 1000;
+
+/// Options of how pairs of branches are analysed
+#[derive(Clone, Debug)]
+pub(crate) struct AnalysisOptions {
+    /// How many commits shallow history can be deepened by to find merge bases
+    pub deepen_limit: u32,
+    /// How git command line tool is run to fetch missing history or objects
+    pub git: GitSettings,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        AnalysisOptions {
+            deepen_limit: DEFAULT_DEEPEN_LIMIT,
+            git: GitSettings::default(),
+        }
+    }
+}
+
+/// Receives progress and results of analysis as each pair of branches is
+/// analysed, so that they can be output before the whole analysis is done
+pub(crate) trait AnalysisSink {
+    /// Analysis starts with given number of pairs of branches
+    fn started(&mut self, _pairs: usize) -> Result<()> {
+        Ok(())
+    }
+
+    /// Pair of branches is about to be analysed
+    fn analysing(&mut self, _from_branch: &str, _into_branch: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Pair of branches has been analysed
+    fn analysed(&mut self, _result: &MergeAnalysisResult) -> Result<()> {
+        Ok(())
+    }
+
+    /// All pairs of branches have been analysed
+    fn finished(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Ignores everything, for when results are only needed once analysis is done
+impl AnalysisSink for () {}
+
+impl<T: AnalysisSink> AnalysisSink for Option<T> {
+    fn started(&mut self, pairs: usize) -> Result<()> {
+        self.as_mut().map_or(Ok(()), |sink| sink.started(pairs))
+    }
+
+    fn analysing(&mut self, from_branch: &str, into_branch: &str) -> Result<()> {
+        self.as_mut()
+            .map_or(Ok(()), |sink| sink.analysing(from_branch, into_branch))
+    }
+
+    fn analysed(&mut self, result: &MergeAnalysisResult) -> Result<()> {
+        self.as_mut().map_or(Ok(()), |sink| sink.analysed(result))
+    }
+
+    fn finished(&mut self) -> Result<()> {
+        self.as_mut().map_or(Ok(()), |sink| sink.finished())
+    }
+}
 
 impl crate::interactive::Analyzer for Probranchinator {
     fn analyse(
         &self,
         repo: Repository,
         branches: Vec<String>,
-        recent: usize,
+        selection: Selection,
+        source: BranchSource,
+        options: AnalysisOptions,
+        sink: &mut dyn AnalysisSink,
     ) -> Result<Vec<MergeAnalysisResult>> {
         let mut answer: Vec<MergeAnalysisResult> = Vec::new();
 
         // get recent branches if none are provided
-        let branches = match branches[..] {
-            [] => get_recent_branches(&repo, recent)?,
-            _ => branches,
-        };
+        let branches = select_branches(&repo, branches, source, &selection)?;
+        let default = default_branch(&repo, source);
 
         // prepare progress indicator
         let branches_length = branches.len();
-        let progress = indicatif::ProgressBar::new(
-            // would be comparing each branch to each other branch except itself
-            (branches_length * branches_length - branches_length).try_into()?,
-        )
-        .with_finish(indicatif::ProgressFinish::AndLeave);
-        progress.enable_steady_tick(Duration::from_millis(100));
-        progress.set_prefix("[2/2]");
-        progress.set_style(
-            ProgressStyle::with_template(
-                "{prefix:.cyan/blue} {spinner} Analysing branches... [{bar:!20}] {wide_msg}",
-            )?
-            .progress_chars("=>-"),
-        );
-
-        let starting_head = repo.head()?;
+        // would be comparing each branch to each other branch except itself
+        let pairs = branches_length * branches_length - branches_length;
+        let progress = start_progress(pairs.try_into()?)?;
+        sink.started(pairs)?;
+
+        // tips are resolved once, so that every pair sees the same commits
+        // of a branch, even if its reference is moved during analysis
+        let tips = branches
+            .iter()
+            .map(|branch| Ok(source.find_reference(&repo, branch)?.peel_to_commit()?.id()))
+            .collect::<Result<Vec<_>>>()?;
+        let mut deepening = Deepening::new(options.deepen_limit, options.git.clone(), tips.clone());
 
         for i in 0..branches_length {
             for j in 0..branches_length {
@@ -51,84 +122,290 @@ impl crate::interactive::Analyzer for Probranchinator {
                 let into_branch = &branches[j];
                 let from_branch = &branches[i];
 
-                progress.inc(1);
-                progress.length().and_then(|length| {
-                    Some(progress.set_message(format!(
-                        "{}/{}: [{} -> {}]",
-                        progress.position(),
-                        length,
-                        from_branch,
-                        into_branch
-                    )))
-                });
-                let their_head =
-                    repo.find_reference(&format!("refs/remotes/origin/{}", from_branch))?;
-                let our_head =
-                    repo.find_reference(&format!("refs/remotes/origin/{}", into_branch))?;
-                let their_commit = repo.reference_to_annotated_commit(&their_head)?;
-                let analysis = repo.merge_analysis_for_ref(&our_head, &[&their_commit])?;
+                advance_progress(&progress, from_branch, into_branch);
+                sink.analysing(from_branch, into_branch)?;
+                let their_commit = tips[i];
+                let our_commit = tips[j];
                 let mut result = MergeAnalysisResult {
                     from_branch: from_branch.clone(),
                     to_branch: into_branch.clone(),
                     status: MergeAnalysisStatus::Unknown,
+                    from_default: default.as_ref() == Some(from_branch),
+                    to_default: default.as_ref() == Some(into_branch),
+                    from_commit: Some(their_commit.to_string()),
+                    to_commit: Some(our_commit.to_string()),
+                    from_committed_at: committed_at(&repo, their_commit),
+                    to_committed_at: committed_at(&repo, our_commit),
+                    ahead: None,
+                    behind: None,
+                    conflicts: Vec::new(),
                 };
-                if analysis.0.is_fast_forward() {
+                // counting walks history too, so it is left out beyond shallow boundary
+                if let Ok((ahead, behind)) = repo.graph_ahead_behind(their_commit, our_commit) {
+                    result.ahead = Some(ahead);
+                    result.behind = Some(behind);
+                }
+                // finding merge base walks history, which might be cut by shallow clone
+                let analysis = match deepening
+                    .run(&repo, || merge_analysis(&repo, our_commit, their_commit))?
+                {
+                    Some(analysis) => analysis,
+                    None => {
+                        result.status = MergeAnalysisStatus::BeyondShallowDepth;
+                        sink.analysed(&result)?;
+                        answer.push(result);
+                        continue;
+                    }
+                };
+                if analysis.is_fast_forward() {
                     result.status = MergeAnalysisStatus::FastForward;
-                } else if analysis.0.is_normal() {
-                    let out_commit = repo.reference_to_annotated_commit(&our_head)?;
-                    match check_normal_merge(&repo, &their_commit, &out_commit) {
-                        Ok(status) => result.status = status,
+                } else if analysis.is_normal() {
+                    match check_normal_merge(
+                        &repo,
+                        their_commit,
+                        our_commit,
+                        &mut deepening,
+                        &options.git,
+                    ) {
+                        Ok((status, conflicts)) => {
+                            result.status = status;
+                            result.conflicts = conflicts;
+                        }
+                        // remote that cannot be reached fails every other pair too
+                        Err(error) if NetworkError::is_cause_of(&error) => return Err(error),
                         Err(error) => {
                             result.status = MergeAnalysisStatus::Error {
-                                message: error.message().to_owned(),
+                                message: match error.downcast_ref::<git2::Error>() {
+                                    Some(error) => error.message().to_owned(),
+                                    None => format!("{:#}", error),
+                                },
                             }
                         }
                     }
-
-                    // this is to clean up the repo after the merge, which can leave dirty files
-                    let starting_head_commit = starting_head.peel_to_commit()?;
-                    repo.reset(
-                        starting_head_commit.as_object(),
-                        git2::ResetType::Hard,
-                        None,
-                    )?;
-                } else if analysis.0.is_up_to_date() {
+                } else if analysis.is_up_to_date() {
                     result.status = MergeAnalysisStatus::UpToDate;
-                } else if analysis.0.is_none() {
+                // `is_none` tests a bit, while no possible merge has no bits set
+                } else if analysis == MergeAnalysis::ANALYSIS_NONE {
                     result.status = MergeAnalysisStatus::None;
                 } else {
                     result.status = MergeAnalysisStatus::Unknown;
                 }
+                sink.analysed(&result)?;
                 answer.push(result);
             }
         }
 
-        // finish progress indicator and display elapsed time
-        progress.set_style(ProgressStyle::with_template(&format!(
-            "{} branches analysed in {{elapsed}}",
-            branches_length
-        ))?);
-        progress.finish_using_style();
+        finish_progress(&progress, branches_length)?;
+        sink.finished()?;
+
+        Ok(answer)
+    }
+
+    fn analyse_working_tree(
+        &self,
+        repo: Repository,
+        branches: Vec<String>,
+        selection: Selection,
+        sink: &mut dyn AnalysisSink,
+    ) -> Result<Vec<MergeAnalysisResult>> {
+        let mut answer: Vec<MergeAnalysisResult> = Vec::new();
+
+        // get recent branches if none are provided
+        let branches = select_branches(&repo, branches, BranchSource::Origin, &selection)?;
+        let default = default_branch(&repo, BranchSource::Origin);
+
+        // working tree is merged into each branch once
+        let branches_length = branches.len();
+        let progress = start_progress(branches_length.try_into()?)?;
+        sink.started(branches_length)?;
+
+        // uncommitted changes are what differs between HEAD and the snapshot,
+        // so HEAD is used as a base for every merge
+        let head_tree = repo
+            .head()
+            .and_then(|head| head.peel_to_tree())
+            .context("failed to read HEAD of the repository")?;
+        let working_tree = snapshot_working_tree(&repo)?;
+
+        for into_branch in &branches {
+            advance_progress(&progress, WORKING_TREE, into_branch);
+            sink.analysing(WORKING_TREE, into_branch)?;
+
+            let our_commit = BranchSource::Origin
+                .find_reference(&repo, into_branch)?
+                .peel_to_commit()?;
+            let our_tree = our_commit.tree()?;
+            let mut conflicts = Vec::new();
+            let status = if working_tree.id() == head_tree.id() {
+                MergeAnalysisStatus::UpToDate
+            } else {
+                match repo.merge_trees(&head_tree, &our_tree, &working_tree, None) {
+                    Ok(idx) if idx.has_conflicts() => {
+                        conflicts = conflicting_paths(&idx)?;
+                        MergeAnalysisStatus::Conflicts
+                    }
+                    Ok(_) => MergeAnalysisStatus::Normal,
+                    Err(error) => MergeAnalysisStatus::Error {
+                        message: error.message().to_owned(),
+                    },
+                }
+            };
+            let result = MergeAnalysisResult {
+                from_branch: WORKING_TREE.to_owned(),
+                to_branch: into_branch.clone(),
+                status,
+                from_default: false,
+                to_default: default.as_ref() == Some(into_branch),
+                from_commit: None,
+                to_commit: Some(our_commit.id().to_string()),
+                from_committed_at: None,
+                to_committed_at: Some(format_commit_time(&our_commit)),
+                ahead: None,
+                behind: None,
+                conflicts,
+            };
+            sink.analysed(&result)?;
+            answer.push(result);
+        }
+
+        finish_progress(&progress, branches_length)?;
+        sink.finished()?;
 
         Ok(answer)
     }
 }
 
+/// Time of the commit, if it can be found in the repository
+fn committed_at(repo: &Repository, id: Oid) -> Option<String> {
+    repo.find_commit(id)
+        .ok()
+        .map(|commit| format_commit_time(&commit))
+}
+
+/// Formats time of the commit in RFC 3339 format, in UTC
+fn format_commit_time(commit: &git2::Commit) -> String {
+    // commits made before the epoch are not expected in practice
+    let seconds = u64::try_from(commit.time().seconds()).unwrap_or_default();
+    humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_secs(seconds)).to_string()
+}
+
+fn start_progress(length: u64) -> Result<ProgressBar> {
+    let progress = ProgressBar::new(length).with_finish(indicatif::ProgressFinish::AndLeave);
+    progress.enable_steady_tick(Duration::from_millis(100));
+    progress.set_prefix("[2/2]");
+    progress.set_style(
+        ProgressStyle::with_template(
+            "{prefix:.cyan/blue} {spinner} Analysing branches... [{bar:!20}] {wide_msg}",
+        )?
+        .progress_chars("=>-"),
+    );
+    Ok(progress)
+}
+
+fn advance_progress(progress: &ProgressBar, from_branch: &str, into_branch: &str) {
+    progress.inc(1);
+    if let Some(length) = progress.length() {
+        progress.set_message(format!(
+            "{}/{}: [{} -> {}]",
+            progress.position(),
+            length,
+            from_branch,
+            into_branch
+        ));
+    }
+}
+
+fn finish_progress(progress: &ProgressBar, branches_length: usize) -> Result<()> {
+    // finish progress indicator and display elapsed time
+    progress.set_style(ProgressStyle::with_template(&format!(
+        "{} branches analysed in {{elapsed}}",
+        branches_length
+    ))?);
+    progress.finish_using_style();
+    Ok(())
+}
+
+/// Writes working tree and index of the repository into a tree without committing.
+///
+/// Repository is expected to be opened with [`crate::interactive::Repo::open_repo`],
+/// so objects created for the snapshot are only kept in memory and neither
+/// object database nor index of the repository on disk are changed.
+fn snapshot_working_tree(repo: &Repository) -> Result<git2::Tree<'_>> {
+    // index is loaded in memory and is never written back
+    let mut index = repo.index()?;
+    index
+        .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+        .context("failed to add working tree changes to index")?;
+    // removes entries for files deleted from working tree
+    index
+        .update_all(["*"].iter(), None)
+        .context("failed to add working tree changes to index")?;
+    let tree_id = index
+        .write_tree()
+        .context("failed to snapshot working tree, resolve merge conflicts first")?;
+    Ok(repo.find_tree(tree_id)?)
+}
+
+/// Tells how commit can be merged into another one, the same way as
+/// [`Repository::merge_analysis_for_ref`] does, but for commits resolved
+/// before analysis instead of references
+fn merge_analysis(repo: &Repository, ours: Oid, theirs: Oid) -> Result<MergeAnalysis, git2::Error> {
+    match repo.merge_base(ours, theirs) {
+        Ok(ancestor) if ancestor == theirs => Ok(MergeAnalysis::ANALYSIS_UP_TO_DATE),
+        Ok(ancestor) if ancestor == ours => {
+            Ok(MergeAnalysis::ANALYSIS_FASTFORWARD | MergeAnalysis::ANALYSIS_NORMAL)
+        }
+        Ok(_) => Ok(MergeAnalysis::ANALYSIS_NORMAL),
+        // unrelated histories have no merge base, so no merge is possible
+        Err(e) if e.code() == ErrorCode::NotFound && e.class() == ErrorClass::Merge => {
+            Ok(MergeAnalysis::ANALYSIS_NONE)
+        }
+        Err(e) => Err(e),
+    }
+}
+
 fn check_normal_merge(
     repo: &Repository,
-    local: &git2::AnnotatedCommit,
-    remote: &git2::AnnotatedCommit,
-) -> Result<MergeAnalysisStatus, git2::Error> {
-    let local_tree = repo.find_commit(local.id())?.tree()?;
-    let remote_tree = repo.find_commit(remote.id())?.tree()?;
-    let ancestor = repo
-        .find_commit(repo.merge_base(local.id(), remote.id())?)?
-        .tree()?;
-    let mut idx = repo.merge_trees(&ancestor, &local_tree, &remote_tree, None)?;
+    local: Oid,
+    remote: Oid,
+    deepening: &mut Deepening,
+    git: &GitSettings,
+) -> Result<(MergeAnalysisStatus, Vec<String>)> {
+    let local = repo.find_commit(local)?;
+    let remote = repo.find_commit(remote)?;
+    let ancestor = match deepening.run(repo, || repo.merge_base(local.id(), remote.id()))? {
+        Some(ancestor) => repo.find_commit(ancestor)?,
+        None => return Ok((MergeAnalysisStatus::BeyondShallowDepth, Vec::new())),
+    };
+    // partial clone lacks trees and blobs until they are needed
+    partial::prefetch_for_merge(repo, git, &ancestor, &local, &remote)?;
+    // merge is only done in memory, resulting index is never written
+    let idx = partial::retry_missing(repo, git, || {
+        repo.merge_trees(&ancestor.tree()?, &local.tree()?, &remote.tree()?, None)
+    })?;
 
     if idx.has_conflicts() {
-        repo.checkout_index(Some(&mut idx), None)?;
-        return Ok(MergeAnalysisStatus::Conflicts);
+        return Ok((MergeAnalysisStatus::Conflicts, conflicting_paths(&idx)?));
+    }
+    Ok((MergeAnalysisStatus::Normal, Vec::new()))
+}
+
+/// Lists paths of files with conflicts in merged index, once for each file
+fn conflicting_paths(idx: &git2::Index) -> Result<Vec<String>, git2::Error> {
+    let mut paths = Vec::new();
+    for conflict in idx.conflicts()? {
+        let conflict = conflict?;
+        // file might be missing on any side, e.g. when it was deleted there
+        let entry = conflict
+            .our
+            .or(conflict.their)
+            .or(conflict.ancestor)
+            .map(|entry| String::from_utf8_lossy(&entry.path).into_owned());
+        if let Some(path) = entry {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
     }
-    Ok(MergeAnalysisStatus::Normal)
+    Ok(paths)
 }
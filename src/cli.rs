@@ -1,13 +1,32 @@
-use std::fmt::Display;
+use std::{fmt::Display, path::PathBuf, time::Duration};
 
-use clap::{arg, command, Parser, ValueEnum};
+use clap::{
+    error::ErrorKind, parser::ValueSource, CommandFactory, FromArgMatches, Parser, Subcommand,
+    ValueEnum,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    git::GitConfigEntry, groups::GroupDefinition, pattern::BranchPattern,
+    pull_request::PullRequestBase,
+};
 
 #[derive(Clone, Debug, ValueEnum)]
 pub(crate) enum OutputType {
     Table,
     Simple,
     Markdown,
+    Matrix,
+    MarkdownMatrix,
+    Html,
+    Junit,
+    Csv,
+    Tsv,
+    Dot,
+    Mermaid,
     Json,
+    Ndjson,
+    Groups,
     Interactive,
 }
 
@@ -17,12 +36,150 @@ impl Display for OutputType {
             OutputType::Table => write!(f, "table"),
             OutputType::Markdown => write!(f, "markdown"),
             OutputType::Simple => write!(f, "simple"),
+            OutputType::Matrix => write!(f, "matrix"),
+            OutputType::MarkdownMatrix => write!(f, "markdown-matrix"),
+            OutputType::Html => write!(f, "html"),
+            OutputType::Junit => write!(f, "junit"),
+            OutputType::Csv => write!(f, "csv"),
+            OutputType::Tsv => write!(f, "tsv"),
+            OutputType::Dot => write!(f, "dot"),
+            OutputType::Mermaid => write!(f, "mermaid"),
             OutputType::Json => write!(f, "json"),
+            OutputType::Ndjson => write!(f, "ndjson"),
+            OutputType::Groups => write!(f, "groups"),
             OutputType::Interactive => write!(f, "interactive"),
         }
     }
 }
 
+/// Column of tabular outputs, named the same as its header
+#[derive(Clone, Copy, Debug, ValueEnum, PartialEq, Eq)]
+#[value(rename_all = "snake_case")]
+pub(crate) enum Column {
+    FromBranch,
+    ToBranch,
+    Status,
+    StatusCode,
+    FromCommit,
+    ToCommit,
+    Ahead,
+    Behind,
+    Conflicts,
+}
+
+impl Display for Column {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Column::FromBranch => write!(f, "from_branch"),
+            Column::ToBranch => write!(f, "to_branch"),
+            Column::Status => write!(f, "status"),
+            Column::StatusCode => write!(f, "status_code"),
+            Column::FromCommit => write!(f, "from_commit"),
+            Column::ToCommit => write!(f, "to_commit"),
+            Column::Ahead => write!(f, "ahead"),
+            Column::Behind => write!(f, "behind"),
+            Column::Conflicts => write!(f, "conflicts"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum, PartialEq, Eq)]
+pub(crate) enum BranchKind {
+    Local,
+    Remote,
+    All,
+}
+
+impl Display for BranchKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BranchKind::Local => write!(f, "local"),
+            BranchKind::Remote => write!(f, "remote"),
+            BranchKind::All => write!(f, "all"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum, PartialEq, Eq)]
+pub(crate) enum SortOrder {
+    #[default]
+    #[value(name = "committerdate")]
+    CommitterDate,
+    #[value(name = "authordate")]
+    AuthorDate,
+    Name,
+    AheadOfBase,
+    BehindBase,
+}
+
+impl Display for SortOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SortOrder::CommitterDate => write!(f, "committerdate"),
+            SortOrder::AuthorDate => write!(f, "authordate"),
+            SortOrder::Name => write!(f, "name"),
+            SortOrder::AheadOfBase => write!(f, "ahead-of-base"),
+            SortOrder::BehindBase => write!(f, "behind-base"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CloneStrategy {
+    Full,
+    #[default]
+    Bare,
+    Blobless,
+    Treeless,
+}
+
+impl Display for CloneStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CloneStrategy::Full => write!(f, "full"),
+            CloneStrategy::Bare => write!(f, "bare"),
+            CloneStrategy::Blobless => write!(f, "blobless"),
+            CloneStrategy::Treeless => write!(f, "treeless"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum, PartialEq, Eq)]
+pub(crate) enum Transport {
+    #[default]
+    Cli,
+    Libgit2,
+}
+
+impl Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Transport::Cli => write!(f, "cli"),
+            Transport::Libgit2 => write!(f, "libgit2"),
+        }
+    }
+}
+
+/// Shape of `json` output
+#[derive(Clone, Copy, Debug, Default, ValueEnum, PartialEq, Eq)]
+pub(crate) enum JsonFormat {
+    /// Versioned object with metadata of the run next to results
+    #[default]
+    Envelope,
+    /// Bare array of results with branch names and statuses only
+    Legacy,
+}
+
+impl Display for JsonFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            JsonFormat::Envelope => write!(f, "envelope"),
+            JsonFormat::Legacy => write!(f, "legacy"),
+        }
+    }
+}
+
 // custom boolean to allow for --pretty to be true by default
 #[derive(Clone, Debug, ValueEnum, PartialEq, Eq)]
 pub(crate) enum BooleanCLI {
@@ -39,22 +196,243 @@ impl Display for BooleanCLI {
     }
 }
 
+#[derive(Subcommand, Debug, PartialEq, Eq)]
+pub(crate) enum Command {
+    /// Manage cached clones of remote repositories
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[derive(Subcommand, Debug, PartialEq, Eq)]
+pub(crate) enum CacheAction {
+    /// List cached clones with their size, last fetch time and branch count
+    List,
+    /// Show details of cached clone of the remote
+    Show {
+        /// Remote repository, same as passed to `--remote`
+        remote: String,
+    },
+    /// Fetch latest changes into cached clone of the remote
+    Refresh {
+        /// Remote repository, same as passed to `--remote`
+        remote: String,
+    },
+    /// Remove cached clones that were not fetched for a while
+    Prune {
+        #[arg(long, value_name = "AGE", value_parser = humantime::parse_duration)]
+        /// Remove clones not fetched for longer than this, e.g. `30d`
+        older_than: Duration,
+    },
+    /// Remove all cached clones
+    Purge,
+}
+
 /// Terminal tool to analyse conflicts between branches in a git repository
 #[derive(Parser, Debug)]
 #[command(name = "probranchinator", version, author)]
 pub(crate) struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[arg(short, long)]
     /// Remote repository to analyse
     ///
     /// This can be a https/ssh URL or file:// path to a local repository.
-    /// Tool would clone the repository in a temporary directory in case
+    /// Tool would clone the repository in a cache directory in case
     /// if it was not cloned before, in which case it would only fetch
     /// the latest changes.
     ///
-    /// This is done to avoid any changes to the working repository, as
-    /// the tool would checkout branches to analyse them in case if it
-    /// needs to detect conflicts.
-    pub remote: String,
+    /// This way branches are analysed exactly as they are on remote,
+    /// regardless of state of your local repository. Use `--repo`
+    /// to analyse local repository directly instead.
+    ///
+    /// If not provided, URL of the remote configured in git repository
+    /// enclosing current directory is used, see `--remote-name`.
+    pub remote: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        global = true,
+        env = "PROBRANCHINATOR_CACHE_DIR"
+    )]
+    /// Directory to cache clones of remote repositories in
+    ///
+    /// Defaults to `probranchinator` under the user's cache directory,
+    /// e.g. `~/.cache/probranchinator` on Linux.
+    pub cache_dir: Option<PathBuf>,
+
+    #[arg(long, default_value = "origin", conflicts_with = "remote")]
+    /// Name of the remote to analyse when `--remote` is not provided
+    ///
+    /// URL of this remote is taken from configuration of git repository
+    /// enclosing current directory.
+    pub remote_name: String,
+
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["remote", "working_tree"])]
+    /// Local repository to analyse directly
+    ///
+    /// Repository is opened as it is, without cloning or fetching, so
+    /// branches that were never pushed can be analysed too. Merges are
+    /// only done in memory, so working tree and index of the repository
+    /// are never touched.
+    pub repo: Option<PathBuf>,
+
+    #[arg(long, default_value_t = BranchKind::Local, requires = "repo")]
+    /// Which branches to analyse in the repository provided with `--repo`
+    ///
+    /// - local - branches under `refs/heads`, named like `main`
+    ///
+    /// - remote - remote-tracking branches, named like `origin/main`
+    ///
+    /// - all - both local and remote-tracking branches
+    pub branch_type: BranchKind,
+
+    #[arg(long, value_name = "PATH", conflicts_with = "remote")]
+    /// Analyse uncommitted changes of a local repository against its branches
+    ///
+    /// Working tree and index of the repository at the given path are
+    /// written into an in-memory tree without committing anything, and
+    /// then merged into each of the selected remote-tracking branches.
+    /// Only uncommitted changes are treated as the side being merged,
+    /// so this shows whether your work in progress would conflict with
+    /// what others have pushed.
+    ///
+    /// Neither working tree nor index of the repository is modified.
+    pub working_tree: Option<PathBuf>,
+
+    #[arg(long, default_value_t = CloneStrategy::Bare, conflicts_with_all = ["repo", "working_tree"])]
+    /// How remote repository is cloned into cache
+    ///
+    /// - bare - all objects, but without working tree
+    ///
+    /// - blobless - without file contents, which are fetched when a merge needs them
+    ///
+    /// - treeless - without directories and file contents, which are fetched
+    ///   when a merge needs them
+    ///
+    /// - full - regular clone with working tree
+    ///
+    /// Partial clones, i.e. blobless and treeless, greatly speed up first
+    /// run on large repositories, but need remote supporting them, which
+    /// most of hosting services do. Cached clone created with another
+    /// strategy is cloned again.
+    pub clone: CloneStrategy,
+
+    #[arg(long, default_value_t = Transport::Cli, conflicts_with_all = ["repo", "working_tree"])]
+    /// How remote repository is cloned and fetched
+    ///
+    /// - cli - with git command line tool, which has to be installed
+    ///
+    /// - libgit2 - with built-in libgit2, which does not need git to be
+    ///   installed, but does not support partial clones. Authenticates with
+    ///   SSH agent, git credential helpers or token from
+    ///   `PROBRANCHINATOR_GIT_TOKEN` environment variable. Support of https
+    ///   and ssh remotes has to be enabled with `https` and `ssh` features
+    ///   when building the tool.
+    pub transport: Transport,
+
+    #[arg(long, conflicts_with_all = ["repo", "working_tree", "max_age"])]
+    /// Use cached clone of remote repository without fetching it
+    ///
+    /// Fails if the remote was never cloned into cache before.
+    pub offline: bool,
+
+    #[arg(long, value_name = "AGE", value_parser = humantime::parse_duration, conflicts_with_all = ["repo", "working_tree"])]
+    /// Only fetch cached clone if it was last fetched longer ago, e.g. `10m`
+    ///
+    /// By default cached clone is fetched on every run.
+    pub max_age: Option<Duration>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        env = "PROBRANCHINATOR_CONFIG",
+        global = true
+    )]
+    /// Configuration file to read settings from
+    ///
+    /// By default `probranchinator/config.toml` under the user's
+    /// configuration directory is read if it exists, e.g.
+    /// `~/.config/probranchinator/config.toml` on Linux.
+    pub config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        env = "PROBRANCHINATOR_GIT_BINARY",
+        global = true
+    )]
+    /// Git executable to run, instead of `git` found in `PATH`
+    pub git_binary: Option<PathBuf>,
+
+    #[arg(
+        short = 'c',
+        long = "git-config",
+        value_name = "KEY=VALUE",
+        global = true
+    )]
+    /// Git configuration passed to every git command, can be repeated
+    ///
+    /// Same as `-c` option of git itself, e.g. `-c http.sslCAInfo=/etc/ca.pem`.
+    pub git_config: Vec<GitConfigEntry>,
+
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, global = true)]
+    /// How long cloning or fetching remote repository can take, e.g. `10m`
+    ///
+    /// Git command that runs longer is killed and counts as failed attempt,
+    /// including fetches of history or objects during analysis. Not limited
+    /// by default. Only applies to `--transport=cli`, as libgit2
+    /// cannot be interrupted.
+    pub fetch_timeout: Option<Duration>,
+
+    #[arg(long, value_name = "COUNT", default_value_t = 2, global = true)]
+    /// How many times failed clone or fetch is retried
+    ///
+    /// Retries wait 1 second at first, doubling the wait for each next one.
+    /// When the last attempt fails too, probranchinator exits with code 3,
+    /// so that network failures can be told apart from failed analysis.
+    pub retries: u32,
+
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, default_value = "5m", global = true)]
+    /// How long to wait for other processes using the same cached clone
+    ///
+    /// Cached clone is locked exclusively while it is cloned or fetched and
+    /// shared during analysis, so parallel runs on the same remote wait
+    /// for each other instead of fetching at once.
+    pub lock_timeout: Duration,
+
+    #[arg(long, value_name = "COMMITS", default_value_t = crate::analysis::DEFAULT_DEEPEN_LIMIT)]
+    /// How many commits history of shallow repository can be deepened by
+    ///
+    /// When merge base of two branches is beyond history of shallow
+    /// repository, more history is fetched from origin, starting with 50
+    /// commits and doubling each time, until merge base is found or this
+    /// limit is reached. Pairs of branches are then reported with their own
+    /// status. Set to 0 to never fetch more history. Only cached clones are
+    /// deepened, repositories given with `--repo` are never fetched into.
+    pub deepen_limit: u32,
+
+    #[arg(long, conflicts_with_all = ["repo", "working_tree"])]
+    /// Fetch GitHub pull requests and GitLab merge requests too
+    ///
+    /// Refs `refs/pull/*/head` and `refs/merge-requests/*/head` are added
+    /// to fetch configuration of the cached repository, so that open pull
+    /// requests, including ones from forks, can be analysed as branches
+    /// named like `pr/42`. Without this option, pull requests fetched
+    /// before are pruned from the cache.
+    pub pull_requests: bool,
+
+    #[arg(long, value_name = "NUMBER=BRANCH")]
+    /// Base branch of pull request, can be repeated
+    ///
+    /// Base branch of each selected pull request is analysed too, so that
+    /// pull request is always compared with the branch it is going to be
+    /// merged into. Default branch of origin is used as base for pull
+    /// requests not listed with this option.
+    pub pr_base: Vec<PullRequestBase>,
 
     #[arg(long, default_value_t = 10)]
     /// Number of recent branches to analyse
@@ -70,23 +448,196 @@ pub(crate) struct Args {
     /// up to the number provided by the `--recent` argument.
     pub branches: Vec<String>,
 
+    #[arg(long, value_name = "PATTERN")]
+    /// Only analyse branches matching the pattern, can be repeated
+    ///
+    /// Pattern is a glob, where `*` matches any sequence of characters,
+    /// including `/`, and `?` matches any single character. Prefix pattern
+    /// with `regex:` to use regular expression, which has to match whole
+    /// branch name.
+    ///
+    /// Branches are filtered before choosing most recent ones. Explicitly
+    /// listed branches that do not match are skipped with a warning.
+    pub include: Vec<BranchPattern>,
+
+    #[arg(long, value_name = "PATTERN")]
+    /// Never analyse branches matching the pattern, can be repeated
+    ///
+    /// Uses same pattern syntax as `--include` and takes precedence over it.
+    pub exclude: Vec<BranchPattern>,
+
+    #[arg(long, default_value_t = SortOrder::CommitterDate)]
+    /// Order in which most recent branches are chosen
+    ///
+    /// - committerdate - most recently committed first
+    ///
+    /// - authordate - most recently authored first
+    ///
+    /// - name - alphabetically by branch name
+    ///
+    /// - ahead-of-base - most commits not in base branch first
+    ///
+    /// - behind-base - most commits of base branch missing first
+    pub sort: SortOrder,
+
+    #[arg(long, value_name = "AGE", value_parser = humantime::parse_duration)]
+    /// Only choose branches with last commit not older than this, e.g. `14d`
+    ///
+    /// Age is measured by committer date of the last commit of a branch and
+    /// can use units like `h`, `d`, `w` or their combination, e.g. `1w 3d`.
+    pub since: Option<Duration>,
+
+    #[arg(long, value_name = "AGE", value_parser = humantime::parse_duration)]
+    /// Only choose branches with last commit at least this old, e.g. `30d`
+    pub until: Option<Duration>,
+
+    #[arg(long)]
+    /// Only choose branches touched by author, whose name or email contains this
+    ///
+    /// Branch is chosen if the last commit or any of commits that are not
+    /// in the base branch was authored by matching author. Matching is
+    /// case-insensitive.
+    pub author: Option<String>,
+
+    #[arg(long)]
+    /// Do not always analyse default branch of origin
+    ///
+    /// By default, when branches are not listed explicitly, the branch
+    /// that `origin/HEAD` points to is always analysed first, even if it
+    /// is not among the most recent ones or does not pass `--since`,
+    /// `--until` or `--author` filters.
+    pub no_default_branch: bool,
+
+    #[arg(long, value_name = "BRANCH")]
+    /// Branch to compare others with for `--sort` and `--author`
+    ///
+    /// Defaults to default branch of origin, which `origin/HEAD` points to.
+    pub base: Option<String>,
+
+    #[arg(long, value_name = "NAME=PATTERN")]
+    /// Define named group of branches, can be repeated
+    ///
+    /// Uses same pattern syntax as `--include`. Repeating the same name
+    /// adds more patterns to the group, e.g. `--group release=release/*
+    /// --group release=hotfix/*`. Branch can belong to several groups.
+    ///
+    /// Groups are used by `groups` output type.
+    pub group: Vec<GroupDefinition>,
+
     #[arg(short, long, default_value_t = OutputType::Interactive)]
     /// How to output the results
     ///
-    /// Choices are `table`, `json` and `interactive`.
-    ///
     /// - table - outputs a table with the results
-    /// 
-    /// - json - outputs results in JSON format
-    /// 
+    ///
+    /// - matrix - outputs a matrix with branches being merged as rows and
+    ///   branches being merged into as columns, `markdown-matrix` for markdown
+    ///
+    /// - html - outputs self-contained HTML report with matrix, list of
+    ///   results and details of each pair of branches
+    ///
+    /// - junit - outputs JUnit XML, where merges that are not possible are
+    ///   failed tests
+    ///
+    /// - csv, tsv - outputs comma or tab separated values for spreadsheets
+    ///
+    /// - dot, mermaid - outputs graph of branches with merges as edges
+    ///   coloured by status, for Graphviz or Mermaid
+    ///
+    /// - json - outputs results in JSON format, see `--json-format`
+    ///
+    /// - ndjson - outputs each result as a JSON line as soon as its pair of
+    ///   branches is analysed, see `--ndjson-progress`
+    ///
+    /// - groups - outputs a matrix summarising merges between groups of
+    ///   branches defined with `--group`
+    ///
     /// - interactive - outputs results in terminal UI
     pub output: OutputType,
 
+    #[arg(long, value_name = "COLUMN", value_delimiter = ',')]
+    /// Columns of tabular outputs, i.e. `table`, `markdown`, `csv` and `tsv`
+    ///
+    /// Comma separated list of `from_branch`, `to_branch`, `status`,
+    /// `status_code`, `from_commit`, `to_commit`, `ahead`, `behind` and
+    /// `conflicts`, where `ahead` and `behind` count commits of branch being
+    /// merged that branch being merged into lacks and the other way around
+    /// and `conflicts` counts conflicting files. Defaults to
+    /// `from_branch,to_branch,status` for `table` and `markdown` and to
+    /// `from_branch,to_branch,status_code,status` for `csv` and `tsv`.
+    pub columns: Vec<Column>,
+
+    #[arg(long)]
+    /// Leave out edges between up-to-date branches in `dot` and `mermaid` outputs
+    pub hide_up_to_date: bool,
+
+    #[arg(long)]
+    /// Draw single undirected edge in `dot` and `mermaid` outputs when
+    /// merges in both directions have the same status
+    pub collapse_symmetric: bool,
+
     #[arg(short, long, default_value_t = BooleanCLI::True)]
     /// If output should be prettified
     ///
     /// Only applicable to `json` output type.
     pub pretty: BooleanCLI,
+
+    #[arg(long, default_value_t = JsonFormat::Envelope)]
+    /// Shape of `json` output
+    ///
+    /// - envelope - outputs object with `schema_version`, `tool_version`,
+    ///   `remote`, `generated_at`, `options`, `branches` with their tip
+    ///   commits and `results`, as described by published JSON Schema
+    ///
+    /// - legacy - outputs bare array of results with only `from_branch`,
+    ///   `to_branch`, `status`, `from_default` and `to_default`, as older
+    ///   versions did
+    pub json_format: JsonFormat,
+
+    #[arg(long)]
+    /// Output events of progress as lines of `ndjson` output
+    ///
+    /// Events are objects with `type` of `started` and `finished` with
+    /// number of `pairs` of branches, and `analysing` with `position` of pair
+    /// being analysed, `from_branch` and `to_branch`. Results never have `type`.
+    pub ndjson_progress: bool,
+}
+
+impl Args {
+    /// Parses command line like [`Parser::parse`], exiting on errors
+    pub fn parse_checked() -> Self {
+        Self::try_parse_checked_from(std::env::args_os()).unwrap_or_else(|error| error.exit())
+    }
+
+    /// Parses command line, failing when options of analysis are passed
+    /// together with a subcommand, which would otherwise ignore them
+    ///
+    /// Options marked as global are shared with subcommands, so they can be
+    /// passed before subcommand, e.g. `--cache-dir cache cache list`.
+    pub fn try_parse_checked_from<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let mut command = Self::command();
+        let matches = command.try_get_matches_from_mut(args)?;
+        if let Some((subcommand, _)) = matches.subcommand() {
+            let conflicting = command.get_arguments().find(|arg| {
+                !arg.is_global_set()
+                    && matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+            });
+            if let Some(arg) = conflicting {
+                let name = arg
+                    .get_long()
+                    .map_or_else(|| arg.get_id().to_string(), |long| format!("--{}", long));
+                let message = format!(
+                    "the argument '{}' cannot be used with subcommand '{}'",
+                    name, subcommand
+                );
+                return Err(command.error(ErrorKind::ArgumentConflict, message));
+            }
+        }
+        Self::from_arg_matches(&matches)
+    }
 }
 
 #[cfg(test)]
@@ -98,13 +649,124 @@ mod tests {
         assert_eq!(OutputType::Table.to_string(), "table");
         assert_eq!(OutputType::Markdown.to_string(), "markdown");
         assert_eq!(OutputType::Simple.to_string(), "simple");
+        assert_eq!(OutputType::Matrix.to_string(), "matrix");
+        assert_eq!(OutputType::MarkdownMatrix.to_string(), "markdown-matrix");
+        assert_eq!(OutputType::Html.to_string(), "html");
+        assert_eq!(OutputType::Junit.to_string(), "junit");
+        assert_eq!(OutputType::Csv.to_string(), "csv");
+        assert_eq!(OutputType::Tsv.to_string(), "tsv");
+        assert_eq!(OutputType::Dot.to_string(), "dot");
+        assert_eq!(OutputType::Mermaid.to_string(), "mermaid");
         assert_eq!(OutputType::Json.to_string(), "json");
+        assert_eq!(OutputType::Ndjson.to_string(), "ndjson");
+        assert_eq!(OutputType::Groups.to_string(), "groups");
         assert_eq!(OutputType::Interactive.to_string(), "interactive");
     }
 
+    #[test]
+    fn test_json_format_display() {
+        assert_eq!(JsonFormat::Envelope.to_string(), "envelope");
+        assert_eq!(JsonFormat::Legacy.to_string(), "legacy");
+    }
+
+    #[test]
+    fn test_column_display() {
+        assert_eq!(Column::FromBranch.to_string(), "from_branch");
+        assert_eq!(Column::ToBranch.to_string(), "to_branch");
+        assert_eq!(Column::Status.to_string(), "status");
+        assert_eq!(Column::StatusCode.to_string(), "status_code");
+        assert_eq!(Column::FromCommit.to_string(), "from_commit");
+        assert_eq!(Column::ToCommit.to_string(), "to_commit");
+        assert_eq!(Column::Ahead.to_string(), "ahead");
+        assert_eq!(Column::Behind.to_string(), "behind");
+        assert_eq!(Column::Conflicts.to_string(), "conflicts");
+    }
+
     #[test]
     fn test_boolean_cli_display() {
         assert_eq!(BooleanCLI::True.to_string(), "true");
         assert_eq!(BooleanCLI::False.to_string(), "false");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_sort_order_display() {
+        assert_eq!(SortOrder::CommitterDate.to_string(), "committerdate");
+        assert_eq!(SortOrder::AuthorDate.to_string(), "authordate");
+        assert_eq!(SortOrder::Name.to_string(), "name");
+        assert_eq!(SortOrder::AheadOfBase.to_string(), "ahead-of-base");
+        assert_eq!(SortOrder::BehindBase.to_string(), "behind-base");
+    }
+
+    #[test]
+    fn test_cache_command() {
+        let args = Args::try_parse_checked_from([
+            "probranchinator",
+            "cache",
+            "prune",
+            "--older-than",
+            "30d",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.command,
+            Some(Command::Cache {
+                action: CacheAction::Prune {
+                    older_than: Duration::from_secs(30 * 24 * 60 * 60)
+                }
+            })
+        );
+
+        let args = Args::try_parse_checked_from([
+            "probranchinator",
+            "--cache-dir",
+            "cache",
+            "cache",
+            "list",
+        ])
+        .unwrap();
+        assert_eq!(args.cache_dir, Some(PathBuf::from("cache")));
+        assert_eq!(
+            args.command,
+            Some(Command::Cache {
+                action: CacheAction::List
+            })
+        );
+
+        // options of analysis would be ignored by subcommand
+        let error = Args::try_parse_checked_from([
+            "probranchinator",
+            "--remote",
+            "https://example.com/x.git",
+            "cache",
+            "list",
+        ])
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ArgumentConflict);
+        assert!(error.to_string().contains("--remote"));
+
+        let args = Args::try_parse_checked_from(["probranchinator", "master", "feature"]).unwrap();
+        assert_eq!(args.command, None);
+        assert_eq!(args.branches, vec!["master", "feature"]);
+    }
+
+    #[test]
+    fn test_clone_strategy_display() {
+        assert_eq!(CloneStrategy::Full.to_string(), "full");
+        assert_eq!(CloneStrategy::Bare.to_string(), "bare");
+        assert_eq!(CloneStrategy::Blobless.to_string(), "blobless");
+        assert_eq!(CloneStrategy::Treeless.to_string(), "treeless");
+    }
+
+    #[test]
+    fn test_transport_display() {
+        assert_eq!(Transport::Cli.to_string(), "cli");
+        assert_eq!(Transport::Libgit2.to_string(), "libgit2");
+    }
+
+    #[test]
+    fn test_branch_kind_display() {
+        assert_eq!(BranchKind::Local.to_string(), "local");
+        assert_eq!(BranchKind::Remote.to_string(), "remote");
+        assert_eq!(BranchKind::All.to_string(), "all");
+    }
+}
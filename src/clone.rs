@@ -1,18 +1,67 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use fehler::throws;
 
+use crate::cli::CloneStrategy;
+use crate::git::GitSettings;
+
 #[throws(eyre::Error)]
-pub(crate) fn clone_repo(repo_url: &str, dst: &PathBuf) {
-    let mut cmd = std::process::Command::new("git");
-    cmd.arg("clone");
-    cmd.arg(repo_url);
-    cmd.arg(dst);
-    let output = cmd.output()?;
-    if !output.status.success() {
-        eyre::bail!(format!(
-            "Failed to clone repository: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
+pub(crate) fn clone_repo(
+    repo_url: &str,
+    dst: &PathBuf,
+    strategy: CloneStrategy,
+    git: &GitSettings,
+) {
+    let filter = match strategy {
+        CloneStrategy::Full => {
+            let target = dst.to_string_lossy();
+            git.with_retries(|| {
+                // failed attempt might leave partial clone behind
+                if dst.exists() {
+                    std::fs::remove_dir_all(dst)?;
+                }
+                git.run_timed(Path::new("."), &["clone", "--quiet", repo_url, &target])
+            })
+            .map_err(|e| e.wrap_err("Failed to clone repository"))?;
+            return;
+        }
+        CloneStrategy::Bare => None,
+        CloneStrategy::Blobless => Some("blob:none"),
+        CloneStrategy::Treeless => Some("tree:0"),
+    };
+
+    // "git clone --bare" would fetch branches into refs/heads, so bare
+    // repository is set up manually to keep them as remote-tracking ones
+    std::fs::create_dir_all(dst)?;
+    run(git, dst, &["init", "--bare", "--quiet"])?;
+    run(git, dst, &["remote", "add", "origin", repo_url])?;
+    if let Some(filter) = filter {
+        // same configuration as "git clone --filter" creates, so that git
+        // fetches missing objects from origin when they are needed
+        run(git, dst, &["config", "remote.origin.promisor", "true"])?;
+        run(
+            git,
+            dst,
+            &["config", "remote.origin.partialclonefilter", filter],
+        )?;
+        run(git, dst, &["config", "extensions.partialclone", "origin"])?;
+    }
+    // fetch resumes from what previous attempts have fetched
+    git.run_network(dst, &["fetch", "--quiet", "origin"])
+        .map_err(|e| e.wrap_err("Failed to clone repository"))?;
+    // origin/HEAD points to default branch of origin,
+    // which empty repository does not have yet
+    if let Err(e) = git.run_network(dst, &["remote", "set-head", "origin", "--auto"]) {
+        log::warn!(
+            "Default branch of {} is not known: {}",
+            crate::git::redact(repo_url),
+            e
+        );
     }
 }
+
+#[throws(eyre::Error)]
+fn run(git: &GitSettings, dir: &Path, args: &[&str]) {
+    git.run(dir, args)
+        .map_err(|e| e.wrap_err("Failed to clone repository"))?;
+}
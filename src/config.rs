@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use eyre::{Context, Result};
+use serde::Deserialize;
+
+use crate::git::{GitConfigEntry, GitSettings};
+use crate::pull_request::PullRequestBase;
+
+/// Settings read from configuration file, which is meant for things that
+/// are the same for every run, e.g. how git is run in a container
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Config {
+    pub git: GitConfig,
+    /// Base branches of pull requests by their numbers
+    pub pr_base: BTreeMap<String, String>,
+}
+
+/// How git command line tool is run
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct GitConfig {
+    /// Path to git executable
+    pub binary: Option<PathBuf>,
+    /// Git configuration passed with `-c` to every git command
+    pub config: BTreeMap<String, String>,
+    /// Command git uses to connect over SSH, set as `GIT_SSH_COMMAND`
+    pub ssh_command: Option<String>,
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    /// Any other environment variables for git
+    pub env: BTreeMap<String, String>,
+}
+
+/// Path to configuration file used when none is given explicitly,
+/// e.g. `~/.config/probranchinator/config.toml` on Linux
+pub(crate) fn default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("probranchinator").join("config.toml"))
+}
+
+impl Config {
+    /// Reads configuration file given explicitly, which has to exist,
+    /// or the default one if it exists
+    pub(crate) fn load(path: Option<&Path>) -> Result<Config> {
+        let path = match path {
+            Some(path) => path.to_owned(),
+            None => match default_config_path() {
+                Some(path) if path.is_file() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read configuration file {:?}", path))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse configuration file {:?}", path))
+    }
+
+    /// Combines git settings of the file with ones given in command line,
+    /// which take precedence over the file
+    pub(crate) fn git_settings(
+        &self,
+        binary: Option<PathBuf>,
+        config: Vec<GitConfigEntry>,
+    ) -> GitSettings {
+        let git = &self.git;
+        let mut env = git.env.clone();
+        let variables = [
+            ("GIT_SSH_COMMAND", &git.ssh_command),
+            ("http_proxy", &git.http_proxy),
+            ("https_proxy", &git.https_proxy),
+            ("no_proxy", &git.no_proxy),
+        ];
+        for (name, value) in variables {
+            if let Some(value) = value {
+                env.insert(name.to_owned(), value.clone());
+            }
+        }
+        GitSettings {
+            binary: binary.or_else(|| git.binary.clone()),
+            // later configuration overrides earlier one in git
+            config: git
+                .config
+                .iter()
+                .map(|(key, value)| GitConfigEntry {
+                    key: key.clone(),
+                    value: value.clone(),
+                })
+                .chain(config)
+                .collect(),
+            env,
+            ..Default::default()
+        }
+    }
+
+    /// Combines base branches of pull requests of the file with ones given
+    /// in command line, which take precedence over the file
+    pub(crate) fn pull_request_bases(
+        &self,
+        bases: Vec<PullRequestBase>,
+    ) -> Result<Vec<PullRequestBase>> {
+        let configured = self
+            .pr_base
+            .iter()
+            .map(|(number, branch)| {
+                format!("{}={}", number, branch)
+                    .parse::<PullRequestBase>()
+                    .map_err(|e| eyre::eyre!("Invalid pr_base in configuration file: {}", e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // first matching base is used, so command line ones go first
+        Ok(bases.into_iter().chain(configured).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_config() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config.toml");
+        fs::write(
+            &path,
+            r#"
+[git]
+binary = "/opt/git/bin/git"
+ssh_command = "ssh -i /keys/deploy"
+https_proxy = "http://proxy:3128"
+config = { "http.sslCAInfo" = "/etc/ca.pem" }
+env = { GIT_TRACE = "1" }
+
+[pr_base]
+42 = "release/1.0"
+"#,
+        )?;
+        let config = Config::load(Some(&path))?;
+
+        let git = config.git_settings(None, vec!["core.askPass=".parse().unwrap()]);
+        assert_eq!(git.binary, Some(PathBuf::from("/opt/git/bin/git")));
+        assert_eq!(
+            git.config
+                .iter()
+                .map(|entry| entry.to_string())
+                .collect::<Vec<_>>(),
+            ["http.sslCAInfo=/etc/ca.pem", "core.askPass="]
+        );
+        assert_eq!(git.env["GIT_SSH_COMMAND"], "ssh -i /keys/deploy");
+        assert_eq!(git.env["https_proxy"], "http://proxy:3128");
+        assert_eq!(git.env["GIT_TRACE"], "1");
+        assert!(!git.env.contains_key("http_proxy"));
+
+        // command line takes precedence
+        let git = config.git_settings(Some("/usr/bin/git".into()), vec![]);
+        assert_eq!(git.binary, Some(PathBuf::from("/usr/bin/git")));
+
+        let bases = config.pull_request_bases(vec!["42=main".parse().unwrap()])?;
+        assert_eq!(bases[0].branch, "main");
+        assert_eq!(bases[1].branch, "release/1.0");
+
+        // explicitly given file has to exist and be valid
+        assert!(Config::load(Some(&dir.path().join("missing.toml"))).is_err());
+        fs::write(&path, "[git]\nbinnary = \"git\"\n")?;
+        assert!(Config::load(Some(&path)).is_err());
+
+        Ok(())
+    }
+}
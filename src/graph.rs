@@ -0,0 +1,307 @@
+use std::fmt::Write;
+
+use crate::{
+    html::write_string,
+    matrix::branches,
+    result::{MergeAnalysisResult, MergeAnalysisStatus},
+};
+
+/// Options of which edges are drawn in graph outputs
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct GraphOptions {
+    /// Leave out edges between branches that are already up-to-date
+    pub hide_up_to_date: bool,
+    /// Draw a single undirected edge when both directions have the same status
+    pub collapse_symmetric: bool,
+}
+
+/// How merge of one branch into another is drawn
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EdgeKind {
+    UpToDate,
+    FastForward,
+    Normal,
+    Conflicts,
+    Unknown,
+}
+
+impl EdgeKind {
+    /// Branches without merge base are not connected, while pairs that could
+    /// not be analysed are drawn as unknown merges
+    fn of(status: &MergeAnalysisStatus) -> Option<EdgeKind> {
+        match status {
+            MergeAnalysisStatus::UpToDate => Some(EdgeKind::UpToDate),
+            MergeAnalysisStatus::FastForward => Some(EdgeKind::FastForward),
+            MergeAnalysisStatus::Normal => Some(EdgeKind::Normal),
+            MergeAnalysisStatus::Conflicts => Some(EdgeKind::Conflicts),
+            MergeAnalysisStatus::None => None,
+            MergeAnalysisStatus::Unknown
+            | MergeAnalysisStatus::BeyondShallowDepth
+            | MergeAnalysisStatus::Error { .. } => Some(EdgeKind::Unknown),
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            EdgeKind::UpToDate => "#9e9e9e",
+            EdgeKind::FastForward => "#2e7d32",
+            EdgeKind::Normal => "#1565c0",
+            EdgeKind::Conflicts => "#c62828",
+            EdgeKind::Unknown => "#f9a825",
+        }
+    }
+
+    /// Whether edge is dashed, as analysis did not tell if merge is possible
+    fn is_dashed(&self) -> bool {
+        *self == EdgeKind::Unknown
+    }
+}
+
+/// Edge between branches, by their positions in list of branches
+#[derive(Debug)]
+struct Edge<'a> {
+    from: usize,
+    to: usize,
+    kind: EdgeKind,
+    directed: bool,
+    result: &'a MergeAnalysisResult,
+}
+
+/// Nodes and edges of the graph, nodes being branches with their labels
+fn graph(
+    results: &[MergeAnalysisResult],
+    options: GraphOptions,
+) -> (Vec<(&str, String)>, Vec<Edge<'_>>) {
+    let branches = branches(results);
+    let position = |name: &str| {
+        branches
+            .iter()
+            .position(|(known, _)| *known == name)
+            .unwrap_or_default()
+    };
+
+    let mut edges: Vec<Edge> = Vec::new();
+    for result in results {
+        let kind = match EdgeKind::of(&result.status) {
+            Some(EdgeKind::UpToDate) if options.hide_up_to_date => continue,
+            Some(kind) => kind,
+            None => continue,
+        };
+        let (from, to) = (position(&result.from_branch), position(&result.to_branch));
+        if options.collapse_symmetric {
+            let reverse = results.iter().find(|other| {
+                other.from_branch == result.to_branch && other.to_branch == result.from_branch
+            });
+            if reverse.is_some_and(|reverse| EdgeKind::of(&reverse.status) == Some(kind)) {
+                // reverse direction has already been drawn as the same edge
+                if edges
+                    .iter()
+                    .any(|edge| !edge.directed && edge.from == to && edge.to == from)
+                {
+                    continue;
+                }
+                edges.push(Edge {
+                    from,
+                    to,
+                    kind,
+                    directed: false,
+                    result,
+                });
+                continue;
+            }
+        }
+        edges.push(Edge {
+            from,
+            to,
+            kind,
+            directed: true,
+            result,
+        });
+    }
+
+    let nodes = branches
+        .into_iter()
+        .map(|(name, default)| {
+            let label = if default {
+                format!("{} (default)", name)
+            } else {
+                name.to_owned()
+            };
+            (name, label)
+        })
+        .collect();
+    (nodes, edges)
+}
+
+/// Renders results as Graphviz DOT graph, with branches as nodes and merges
+/// as edges coloured by their status
+pub(crate) fn render_dot(results: &[MergeAnalysisResult], options: GraphOptions) -> String {
+    let (nodes, edges) = graph(results, options);
+    write_string(|dot| write_dot(dot, &nodes, &edges))
+}
+
+fn write_dot(dot: &mut String, nodes: &[(&str, String)], edges: &[Edge]) -> std::fmt::Result {
+    writeln!(dot, "digraph probranchinator {{")?;
+    writeln!(dot, "  rankdir=LR;")?;
+    writeln!(dot, "  node [shape=box];")?;
+    for (name, label) in nodes {
+        writeln!(dot, "  {} [label={}];", dot_string(name), dot_string(label))?;
+    }
+    for edge in edges {
+        let mut attributes = vec![
+            format!("color={}", dot_string(edge.kind.color())),
+            format!("tooltip={}", dot_string(&edge.result.status.to_string())),
+        ];
+        if !edge.directed {
+            attributes.push("dir=none".to_owned());
+        }
+        if edge.kind.is_dashed() {
+            attributes.push("style=dashed".to_owned());
+        }
+        writeln!(
+            dot,
+            "  {} -> {} [{}];",
+            dot_string(nodes[edge.from].0),
+            dot_string(nodes[edge.to].0),
+            attributes.join(", ")
+        )?;
+    }
+    writeln!(dot, "}}")
+}
+
+/// Renders results as Mermaid flowchart, which can be embedded in markdown
+pub(crate) fn render_mermaid(results: &[MergeAnalysisResult], options: GraphOptions) -> String {
+    let (nodes, edges) = graph(results, options);
+    write_string(|mermaid| write_mermaid(mermaid, &nodes, &edges))
+}
+
+fn write_mermaid(
+    mermaid: &mut String,
+    nodes: &[(&str, String)],
+    edges: &[Edge],
+) -> std::fmt::Result {
+    writeln!(mermaid, "flowchart LR")?;
+    // branch names may contain characters mermaid does not allow in ids
+    for (i, (_, label)) in nodes.iter().enumerate() {
+        writeln!(mermaid, "  b{}[\"{}\"]", i, mermaid_text(label))?;
+    }
+    for edge in edges {
+        let arrow = match (edge.directed, edge.kind.is_dashed()) {
+            (true, false) => "-->",
+            (false, false) => "---",
+            (true, true) => "-.->",
+            (false, true) => "-.-",
+        };
+        writeln!(mermaid, "  b{} {} b{}", edge.from, arrow, edge.to)?;
+    }
+    // links are styled by their order of appearance
+    for (i, edge) in edges.iter().enumerate() {
+        writeln!(mermaid, "  linkStyle {} stroke:{}", i, edge.kind.color())?;
+    }
+    Ok(())
+}
+
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn mermaid_text(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn result(from: &str, to: &str, status: MergeAnalysisStatus) -> MergeAnalysisResult {
+        MergeAnalysisResult {
+            from_branch: from.to_string(),
+            to_branch: to.to_string(),
+            status,
+            from_default: from == "main",
+            to_default: to == "main",
+            ..Default::default()
+        }
+    }
+
+    fn results() -> Vec<MergeAnalysisResult> {
+        vec![
+            result("main", "feature", MergeAnalysisStatus::FastForward),
+            result("main", "other", MergeAnalysisStatus::Conflicts),
+            result("main", "orphan", MergeAnalysisStatus::None),
+            result("feature", "main", MergeAnalysisStatus::UpToDate),
+            result("feature", "other", MergeAnalysisStatus::Normal),
+            result("other", "main", MergeAnalysisStatus::Conflicts),
+            result("other", "feature", MergeAnalysisStatus::Normal),
+        ]
+    }
+
+    #[test]
+    fn test_render_dot() {
+        assert_eq!(
+            render_dot(&results(), GraphOptions::default()),
+            r##"digraph probranchinator {
+  rankdir=LR;
+  node [shape=box];
+  "main" [label="main (default)"];
+  "feature" [label="feature"];
+  "other" [label="other"];
+  "orphan" [label="orphan"];
+  "main" -> "feature" [color="#2e7d32", tooltip="🚀✅ No confilcts: fast-forward merge is possible."];
+  "main" -> "other" [color="#c62828", tooltip="🚧🔧 Found conflicts, have to resolve them manually."];
+  "feature" -> "main" [color="#9e9e9e", tooltip="✅✅ No changes: already up-to-date."];
+  "feature" -> "other" [color="#1565c0", tooltip="🤝✅ No conflicts: automatic merge is possible."];
+  "other" -> "main" [color="#c62828", tooltip="🚧🔧 Found conflicts, have to resolve them manually."];
+  "other" -> "feature" [color="#1565c0", tooltip="🤝✅ No conflicts: automatic merge is possible."];
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_render_mermaid() {
+        let options = GraphOptions {
+            hide_up_to_date: true,
+            collapse_symmetric: true,
+        };
+        assert_eq!(
+            render_mermaid(&results(), options),
+            r#"flowchart LR
+  b0["main (default)"]
+  b1["feature"]
+  b2["other"]
+  b3["orphan"]
+  b0 --> b1
+  b0 --- b2
+  b1 --- b2
+  linkStyle 0 stroke:#2e7d32
+  linkStyle 1 stroke:#c62828
+  linkStyle 2 stroke:#1565c0
+"#
+        );
+    }
+
+    #[test]
+    fn test_render_error_as_unknown() {
+        let results = vec![
+            result("main", "feature", MergeAnalysisStatus::None),
+            result(
+                "feature",
+                "main",
+                MergeAnalysisStatus::Error {
+                    message: "missing object".to_string(),
+                },
+            ),
+        ];
+        assert_eq!(
+            render_mermaid(&results, GraphOptions::default()),
+            r#"flowchart LR
+  b0["main (default)"]
+  b1["feature"]
+  b1 -.-> b0
+  linkStyle 0 stroke:#f9a825
+"#
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use std::{fmt::Display, str::FromStr};
+
+use tabled::builder::Builder;
+
+use crate::{
+    pattern::BranchPattern,
+    result::{MergeAnalysisResult, MergeAnalysisStatus},
+};
+
+/// Named group of branches, defined as `NAME=PATTERN`
+///
+/// Same name can be used several times to add more patterns to the group.
+#[derive(Clone, Debug)]
+pub(crate) struct GroupDefinition {
+    pub name: String,
+    pub pattern: BranchPattern,
+}
+
+impl FromStr for GroupDefinition {
+    type Err = String;
+
+    fn from_str(definition: &str) -> Result<Self, Self::Err> {
+        let (name, pattern) = definition
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=PATTERN, got '{}'", definition))?;
+        if name.is_empty() {
+            return Err(format!("group name is empty in '{}'", definition));
+        }
+        Ok(GroupDefinition {
+            name: name.to_owned(),
+            pattern: pattern.parse().map_err(|e| format!("{}", e))?,
+        })
+    }
+}
+
+/// Group of branches matching any of its patterns
+#[derive(Debug)]
+struct BranchGroup {
+    name: String,
+    patterns: Vec<BranchPattern>,
+}
+
+impl BranchGroup {
+    fn matches(&self, branch: &str) -> bool {
+        self.patterns.iter().any(|pattern| pattern.matches(branch))
+    }
+}
+
+/// Merges repeated definitions into groups, keeping order of first appearance
+fn collect_groups(definitions: &[GroupDefinition]) -> Vec<BranchGroup> {
+    let mut groups: Vec<BranchGroup> = Vec::new();
+    for definition in definitions {
+        match groups
+            .iter_mut()
+            .find(|group| group.name == definition.name)
+        {
+            Some(group) => group.patterns.push(definition.pattern.clone()),
+            None => groups.push(BranchGroup {
+                name: definition.name.clone(),
+                patterns: vec![definition.pattern.clone()],
+            }),
+        }
+    }
+    groups
+}
+
+/// Counts of merge results between branches of two groups
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct GroupSummary {
+    pub clean: usize,
+    pub conflicts: usize,
+    pub impossible: usize,
+    pub unknown: usize,
+}
+
+impl GroupSummary {
+    fn add(&mut self, status: &MergeAnalysisStatus) {
+        match status {
+            MergeAnalysisStatus::UpToDate
+            | MergeAnalysisStatus::FastForward
+            | MergeAnalysisStatus::Normal => self.clean += 1,
+            MergeAnalysisStatus::Conflicts => self.conflicts += 1,
+            MergeAnalysisStatus::None | MergeAnalysisStatus::Error { .. } => self.impossible += 1,
+            MergeAnalysisStatus::Unknown | MergeAnalysisStatus::BeyondShallowDepth => {
+                self.unknown += 1
+            }
+        }
+    }
+}
+
+impl Display for GroupSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let conflicts = match self.conflicts {
+            1 => "conflict",
+            _ => "conflicts",
+        };
+        let parts = [
+            (self.clean, "clean"),
+            (self.conflicts, conflicts),
+            (self.impossible, "no merge"),
+            (self.unknown, "unknown"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, label)| format!("{} {}", count, label))
+        .collect::<Vec<_>>();
+        if parts.is_empty() {
+            write!(f, "-")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
+
+/// Aggregates results into matrix, where rows are groups of branches being
+/// merged and columns are groups of branches being merged into
+pub(crate) fn summarize(
+    results: &[MergeAnalysisResult],
+    definitions: &[GroupDefinition],
+) -> (Vec<String>, Vec<Vec<GroupSummary>>) {
+    let groups = collect_groups(definitions);
+    let mut matrix = groups
+        .iter()
+        .map(|_| groups.iter().map(|_| GroupSummary::default()).collect())
+        .collect::<Vec<Vec<_>>>();
+    for result in results {
+        for (from, from_group) in groups.iter().enumerate() {
+            if !from_group.matches(&result.from_branch) {
+                continue;
+            }
+            for (into, into_group) in groups.iter().enumerate() {
+                if into_group.matches(&result.to_branch) {
+                    matrix[from][into].add(&result.status);
+                }
+            }
+        }
+    }
+    (groups.into_iter().map(|group| group.name).collect(), matrix)
+}
+
+/// Builds table with summaries of merges between groups of branches
+pub(crate) fn summary_table(
+    results: &[MergeAnalysisResult],
+    definitions: &[GroupDefinition],
+) -> tabled::Table {
+    let (names, matrix) = summarize(results, definitions);
+    let mut builder = Builder::default();
+    builder.set_header(std::iter::once("from \\ into".to_owned()).chain(names.iter().cloned()));
+    for (name, row) in names.iter().zip(matrix) {
+        builder.push_record(
+            std::iter::once(name.clone()).chain(row.iter().map(|summary| summary.to_string())),
+        );
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn result(from: &str, to: &str, status: MergeAnalysisStatus) -> MergeAnalysisResult {
+        MergeAnalysisResult {
+            from_branch: from.to_string(),
+            to_branch: to.to_string(),
+            status,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_group_definition_parse() {
+        let definition: GroupDefinition = "release=release/*".parse().unwrap();
+        assert_eq!(definition.name, "release");
+        assert!(definition.pattern.matches("release/1.0"));
+        assert!("release".parse::<GroupDefinition>().is_err());
+        assert!("=release/*".parse::<GroupDefinition>().is_err());
+    }
+
+    #[test]
+    fn test_summary_table() {
+        let definitions: Vec<GroupDefinition> = vec![
+            "release=release/*".parse().unwrap(),
+            "main=main".parse().unwrap(),
+            "release=hotfix".parse().unwrap(),
+        ];
+        let results = vec![
+            result("release/1", "main", MergeAnalysisStatus::Normal),
+            result("release/2", "main", MergeAnalysisStatus::FastForward),
+            result("hotfix", "main", MergeAnalysisStatus::UpToDate),
+            result("release/3", "main", MergeAnalysisStatus::Conflicts),
+            result("main", "release/1", MergeAnalysisStatus::Conflicts),
+            result("main", "release/2", MergeAnalysisStatus::Conflicts),
+            result("release/1", "release/2", MergeAnalysisStatus::None),
+            result("feature", "main", MergeAnalysisStatus::Normal),
+        ];
+
+        let (names, matrix) = summarize(&results, &definitions);
+        assert_eq!(names, vec!["release", "main"]);
+        assert_eq!(
+            matrix[0][1],
+            GroupSummary {
+                clean: 3,
+                conflicts: 1,
+                ..Default::default()
+            }
+        );
+
+        let expected = r#"
++-------------+-------------+---------------------+
+| from \ into | release     | main                |
++-------------+-------------+---------------------+
+| release     | 1 no merge  | 3 clean, 1 conflict |
++-------------+-------------+---------------------+
+| main        | 2 conflicts | -                   |
++-------------+-------------+---------------------+
+"#
+        .trim();
+        assert_eq!(summary_table(&results, &definitions).to_string(), expected);
+    }
+}
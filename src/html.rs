@@ -0,0 +1,344 @@
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use crate::{
+    matrix::{Matrix, MatrixCell},
+    result::MergeAnalysisResult,
+};
+
+/// Styles of the report, inlined so that it works offline
+const STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }
+th { background: #f0f0f0; }
+td a { color: inherit; text-decoration: none; }
+code { font-size: 0.9em; }
+.matrix td { text-align: center; }
+.up_to_date, .fast_forward { background: #c8e6c9; }
+.normal { background: #bbdefb; }
+.conflicts { background: #ffcdd2; }
+.none, .error { background: #e0e0e0; }
+.unknown, .beyond_shallow_depth { background: #fff9c4; }
+#results th { cursor: pointer; }
+#results th::after { content: " \2195"; color: #999; }
+details { margin: 0.3em 0; }
+summary { cursor: pointer; }
+"#;
+
+/// Sorts list of results by clicked column, filters it by text and status
+/// and opens details of pair linked from matrix or list
+const SCRIPT: &str = r##"
+const rows = Array.from(document.querySelectorAll("#results tbody tr"));
+const text = document.getElementById("filter");
+const status = document.getElementById("status-filter");
+function filter() {
+  const query = text.value.toLowerCase();
+  for (const row of rows) {
+    const shown = row.textContent.toLowerCase().includes(query)
+      && (status.value === "" || row.dataset.status === status.value);
+    row.hidden = !shown;
+  }
+}
+text.addEventListener("input", filter);
+status.addEventListener("change", filter);
+document.querySelectorAll("#results th").forEach((header, column) => {
+  let ascending = true;
+  header.addEventListener("click", () => {
+    const body = document.querySelector("#results tbody");
+    rows.sort((a, b) => {
+      const x = a.children[column].dataset.sort ?? a.children[column].textContent;
+      const y = b.children[column].dataset.sort ?? b.children[column].textContent;
+      return (ascending ? 1 : -1) * x.localeCompare(y, undefined, { numeric: true });
+    });
+    ascending = !ascending;
+    rows.forEach(row => body.appendChild(row));
+  });
+});
+function openLinked() {
+  const details = document.getElementById(location.hash.slice(1));
+  if (details) details.open = true;
+}
+window.addEventListener("hashchange", openLinked);
+openLinked();
+"##;
+
+/// Details of the run shown in the header of report
+#[derive(Debug)]
+pub(crate) struct ReportMetadata {
+    /// Remote or path of repository that was analysed
+    pub source: String,
+    pub generated_at: SystemTime,
+    /// Time of the last fetch, if cached data was used without fetching it
+    pub fetched_at: Option<SystemTime>,
+}
+
+/// Renders results as self-contained HTML page, with matrix of branches,
+/// list of results that can be sorted and filtered and details of each pair
+pub(crate) fn render(results: &[MergeAnalysisResult], metadata: &ReportMetadata) -> String {
+    write_string(|html| write_report(html, results, metadata))
+}
+
+fn write_report(
+    html: &mut String,
+    results: &[MergeAnalysisResult],
+    metadata: &ReportMetadata,
+) -> std::fmt::Result {
+    let title = format!("Merge analysis of {}", metadata.source);
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html lang=\"en\">")?;
+    writeln!(html, "<head>")?;
+    writeln!(html, "<meta charset=\"utf-8\">")?;
+    writeln!(html, "<title>{}</title>", escape(&title))?;
+    writeln!(html, "<style>{}</style>", STYLE)?;
+    writeln!(html, "</head>")?;
+    writeln!(html, "<body>")?;
+    writeln!(html, "<h1>{}</h1>", escape(&title))?;
+
+    write_metadata(html, results, metadata)?;
+    write_matrix(html, results)?;
+    write_results(html, results)?;
+    write_details(html, results)?;
+
+    writeln!(html, "<script>{}</script>", SCRIPT)?;
+    writeln!(html, "</body>")?;
+    writeln!(html, "</html>")
+}
+
+fn write_metadata(
+    html: &mut String,
+    results: &[MergeAnalysisResult],
+    metadata: &ReportMetadata,
+) -> std::fmt::Result {
+    write!(html, "<p>Generated at {}", time(metadata.generated_at))?;
+    if let Some(fetched_at) = metadata.fetched_at {
+        write!(html, " from cached data fetched at {}", time(fetched_at))?;
+    }
+    writeln!(
+        html,
+        " by probranchinator {}.</p>",
+        env!("CARGO_PKG_VERSION")
+    )?;
+
+    writeln!(html, "<h2>Branches</h2>")?;
+    writeln!(html, "<table>")?;
+    writeln!(html, "<tr><th>Branch</th><th>Commit</th></tr>")?;
+    for (name, commit) in branch_commits(results) {
+        writeln!(
+            html,
+            "<tr><td>{}</td><td><code>{}</code></td></tr>",
+            escape(&name),
+            escape(commit.unwrap_or("-"))
+        )?;
+    }
+    writeln!(html, "</table>")
+}
+
+/// Lists branches with their tip commits, in order they appear in results
+fn branch_commits(results: &[MergeAnalysisResult]) -> Vec<(String, Option<&str>)> {
+    let mut branches: Vec<(String, Option<&str>)> = Vec::new();
+    for result in results {
+        for (name, commit) in [
+            (result.display_from_branch(), &result.from_commit),
+            (result.display_to_branch(), &result.to_commit),
+        ] {
+            if !branches.iter().any(|(known, _)| *known == name) {
+                branches.push((name, commit.as_deref()));
+            }
+        }
+    }
+    branches
+}
+
+fn write_matrix(html: &mut String, results: &[MergeAnalysisResult]) -> std::fmt::Result {
+    let matrix = Matrix::new(results);
+    writeln!(html, "<h2>Matrix</h2>")?;
+    writeln!(html, "<table class=\"matrix\">")?;
+    write!(html, "<tr><th>from \\ into</th>")?;
+    for column in &matrix.columns {
+        write!(html, "<th>{}</th>", escape(column))?;
+    }
+    writeln!(html, "</tr>")?;
+    for (label, row) in matrix.rows.iter().zip(&matrix.cells) {
+        write!(html, "<tr><th>{}</th>", escape(label))?;
+        for cell in row {
+            match cell {
+                MatrixCell::Result(result) => write!(
+                    html,
+                    "<td class=\"{}\" title=\"{}\"><a href=\"#{}\">{}</a></td>",
+                    result.status.code(),
+                    escape(&result.to_string()),
+                    pair_id(results, result),
+                    cell.short()
+                )?,
+                _ => write!(html, "<td>{}</td>", cell.short())?,
+            }
+        }
+        writeln!(html, "</tr>")?;
+    }
+    writeln!(html, "</table>")?;
+    writeln!(html, "<ul>")?;
+    for line in matrix.legend().iter().chain(&matrix.footnote_lines()) {
+        writeln!(html, "<li>{}</li>", escape(line))?;
+    }
+    writeln!(html, "</ul>")
+}
+
+fn write_results(html: &mut String, results: &[MergeAnalysisResult]) -> std::fmt::Result {
+    writeln!(html, "<h2>Results</h2>")?;
+    writeln!(
+        html,
+        "<p><input id=\"filter\" type=\"search\" placeholder=\"Filter by text\">"
+    )?;
+    writeln!(html, "<select id=\"status-filter\">")?;
+    writeln!(html, "<option value=\"\">All statuses</option>")?;
+    let mut codes: Vec<&str> = Vec::new();
+    for result in results {
+        let code = result.status.code();
+        if !codes.contains(&code) {
+            codes.push(code);
+            writeln!(html, "<option value=\"{0}\">{0}</option>", code)?;
+        }
+    }
+    writeln!(html, "</select></p>")?;
+
+    writeln!(html, "<table id=\"results\">")?;
+    writeln!(
+        html,
+        "<thead><tr><th>From</th><th>Into</th><th>Status</th><th>Conflicting files</th></tr></thead>"
+    )?;
+    writeln!(html, "<tbody>")?;
+    for result in results {
+        writeln!(
+            html,
+            "<tr data-status=\"{code}\"><td>{}</td><td>{}</td><td class=\"{code}\" data-sort=\"{code}\"><a href=\"#{}\">{}</a></td><td>{}</td></tr>",
+            escape(&result.display_from_branch()),
+            escape(&result.display_to_branch()),
+            pair_id(results, result),
+            escape(&result.status.to_string()),
+            result.conflicts.len(),
+            code = result.status.code(),
+        )?;
+    }
+    writeln!(html, "</tbody>")?;
+    writeln!(html, "</table>")
+}
+
+fn write_details(html: &mut String, results: &[MergeAnalysisResult]) -> std::fmt::Result {
+    writeln!(html, "<h2>Details</h2>")?;
+    for result in results {
+        writeln!(html, "<details id=\"{}\">", pair_id(results, result))?;
+        writeln!(html, "<summary>{}</summary>", escape(&result.to_string()))?;
+        writeln!(html, "<ul>")?;
+        for (name, commit) in [
+            (result.display_from_branch(), &result.from_commit),
+            (result.display_to_branch(), &result.to_commit),
+        ] {
+            if let Some(commit) = commit {
+                writeln!(
+                    html,
+                    "<li>{} at <code>{}</code></li>",
+                    escape(&name),
+                    escape(commit)
+                )?;
+            }
+        }
+        writeln!(html, "</ul>")?;
+        if !result.conflicts.is_empty() {
+            writeln!(html, "<p>Conflicting files:</p>")?;
+            writeln!(html, "<ul>")?;
+            for path in &result.conflicts {
+                writeln!(html, "<li><code>{}</code></li>", escape(path))?;
+            }
+            writeln!(html, "</ul>")?;
+        }
+        writeln!(html, "</details>")?;
+    }
+    Ok(())
+}
+
+/// Anchor of details of the result, which is its position in results
+fn pair_id(results: &[MergeAnalysisResult], result: &MergeAnalysisResult) -> String {
+    let position = results
+        .iter()
+        .position(|other| std::ptr::eq(other, result))
+        .unwrap_or_default();
+    format!("pair-{}", position + 1)
+}
+
+fn time(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time).to_string()
+}
+
+/// Collects report written by given function into string
+pub(crate) fn write_string(write: impl FnOnce(&mut String) -> std::fmt::Result) -> String {
+    let mut text = String::new();
+    write(&mut text).expect("writing to String cannot fail");
+    text
+}
+
+/// Escapes text for HTML or XML, in content and attributes alike
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::MergeAnalysisStatus;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_render() {
+        let results = vec![
+            MergeAnalysisResult {
+                from_branch: "feature/<b>".to_string(),
+                to_branch: "main".to_string(),
+                status: MergeAnalysisStatus::Conflicts,
+                to_default: true,
+                from_commit: Some("1111111".to_string()),
+                to_commit: Some("2222222".to_string()),
+                conflicts: vec!["src/lib.rs".to_string()],
+                ..Default::default()
+            },
+            MergeAnalysisResult {
+                from_branch: "main".to_string(),
+                to_branch: "feature/<b>".to_string(),
+                status: MergeAnalysisStatus::FastForward,
+                from_default: true,
+                from_commit: Some("2222222".to_string()),
+                to_commit: Some("1111111".to_string()),
+                ..Default::default()
+            },
+        ];
+        let html = render(
+            &results,
+            &ReportMetadata {
+                source: "https://github.com/strowk/probranchinator-test.git".to_string(),
+                generated_at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+                fetched_at: None,
+            },
+        );
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(
+            "<h1>Merge analysis of https://github.com/strowk/probranchinator-test.git</h1>"
+        ));
+        assert!(html.contains("Generated at 2023-11-14T22:13:20Z by"));
+        assert!(html.contains("<tr><td>feature/&lt;b&gt;</td><td><code>1111111</code></td></tr>"));
+        assert!(html.contains("<td class=\"conflicts\" title=\"feature/&lt;b&gt; -&gt; main (default) : 🚧🔧 Found conflicts, have to resolve them manually.\"><a href=\"#pair-1\">🚧🔧</a></td>"));
+        assert!(html.contains("<tr data-status=\"fast_forward\">"));
+        assert!(html.contains("<option value=\"conflicts\">conflicts</option>"));
+        assert!(html.contains("<details id=\"pair-1\">"));
+        assert!(html.contains("<li><code>src/lib.rs</code></li>"));
+        assert!(!html.contains("<b>"));
+
+        // nothing is loaded from elsewhere, so that report works offline
+        assert!(!html.contains("src=\""));
+        assert!(!html.contains("<link"));
+    }
+}
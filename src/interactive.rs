@@ -1,4 +1,11 @@
-use crate::result::MergeAnalysisResult;
+use crate::{
+    analysis::{AnalysisOptions, AnalysisSink},
+    lock::CacheLock,
+    matrix::Matrix,
+    recent::{BranchSource, Selection},
+    repo::RepoOptions,
+    result::MergeAnalysisResult,
+};
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
     execute,
@@ -18,20 +25,25 @@ use tui::{
     layout::{Constraint, Layout},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, Borders, Cell, Row, Table, TableState},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
     Frame, Terminal,
 };
 
 struct App {
     state: TableState,
     items: Vec<MergeAnalysisResult>,
+    /// Whether results are shown as matrix instead of list
+    matrix: bool,
 }
 
 impl App {
-    fn new(answer: Vec<MergeAnalysisResult>) -> App {
+    fn new(mut answer: Vec<MergeAnalysisResult>) -> App {
+        // merges from and into default branch are usually most interesting
+        answer.sort_by_key(|item| !(item.from_default || item.to_default));
         App {
             state: TableState::default(),
             items: answer,
+            matrix: false,
         }
     }
     pub fn next(&mut self) {
@@ -78,13 +90,32 @@ pub(crate) trait Analyzer {
         &self,
         repo: Repository,
         branches: Vec<String>,
-        recent: usize,
+        selection: Selection,
+        source: BranchSource,
+        options: AnalysisOptions,
+        sink: &mut dyn AnalysisSink,
+    ) -> eyre::Result<Vec<MergeAnalysisResult>>;
+
+    fn analyse_working_tree(
+        &self,
+        repo: Repository,
+        branches: Vec<String>,
+        selection: Selection,
+        sink: &mut dyn AnalysisSink,
     ) -> eyre::Result<Vec<MergeAnalysisResult>>;
 }
 
 #[cfg_attr(test, automock)]
 pub(crate) trait Repo {
-    fn get_repo(&self, remote: &str) -> eyre::Result<(Repository, std::path::PathBuf, bool)>;
+    fn get_repo(
+        &self,
+        remote: &str,
+        options: &RepoOptions,
+    ) -> eyre::Result<(Repository, std::path::PathBuf, bool, Option<CacheLock>)>;
+
+    fn open_repo(&self, path: &std::path::Path) -> eyre::Result<Repository>;
+
+    fn discover_remote(&self, path: &std::path::Path, remote_name: &str) -> eyre::Result<String>;
 }
 
 #[throws(eyre::Error)]
@@ -132,14 +163,14 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> eyre::Result
         if let Event::Key(key) = event::read()? {
             match key.code {
                 KeyCode::Char('q') => return Ok(()),
-                KeyCode::Char('c') => {
+                KeyCode::Char('c')
                     if key
                         .modifiers
-                        .contains(crossterm::event::KeyModifiers::CONTROL)
-                    {
-                        return Ok(());
-                    }
+                        .contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    return Ok(());
                 }
+                KeyCode::Char('m') => app.matrix = !app.matrix,
                 KeyCode::Down => app.next(),
                 KeyCode::Up => app.previous(),
                 _ => {}
@@ -149,6 +180,9 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> eyre::Result
 }
 
 fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    if app.matrix {
+        return matrix_ui(f, app);
+    }
     let rects = Layout::default()
         .constraints([Constraint::Percentage(100)].as_ref())
         .margin(1)
@@ -161,12 +195,12 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
             Cell::from(item.status.to_string()),
             Cell::from(Spans::from(vec![
                 Span::styled(
-                    &item.from_branch,
+                    item.display_from_branch(),
                     Style::default().add_modifier(Modifier::BOLD),
                 ),
                 Span::styled(" -> ", Style::default()),
                 Span::styled(
-                    &item.to_branch,
+                    item.display_to_branch(),
                     Style::default().add_modifier(Modifier::BOLD),
                 ),
             ])),
@@ -195,6 +229,58 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     f.render_stateful_widget(t, rects[0], &mut app.state);
 }
 
+fn matrix_ui<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let matrix = Matrix::new(&app.items);
+    let notes = matrix
+        .legend()
+        .into_iter()
+        .chain(matrix.footnote_lines())
+        .map(Spans::from)
+        .collect::<Vec<_>>();
+    let rects = Layout::default()
+        .constraints([Constraint::Min(0), Constraint::Length(notes.len() as u16)].as_ref())
+        .margin(1)
+        .split(f.size());
+
+    let header_style = Style::default().fg(Color::Red);
+    let header = Row::new(
+        std::iter::once("from \\ into")
+            .chain(matrix.columns.iter().map(String::as_str))
+            .map(|label| Cell::from(label.to_owned()).style(header_style)),
+    )
+    .style(Style::default().bg(Color::Blue));
+    let rows = matrix.rows.iter().zip(&matrix.cells).map(|(label, cells)| {
+        Row::new(
+            std::iter::once(
+                Cell::from(label.clone()).style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .chain(cells.iter().map(|cell| Cell::from(cell.short()))),
+        )
+    });
+
+    // columns are as wide as their labels, but fit at least two emoji
+    let label_width = |label: &String| label.chars().count() as u16;
+    let widths = std::iter::once(
+        matrix
+            .rows
+            .iter()
+            .map(label_width)
+            .max()
+            .unwrap_or_default()
+            .max(11),
+    )
+    .chain(matrix.columns.iter().map(|label| label_width(label).max(4)))
+    .map(Constraint::Length)
+    .collect::<Vec<_>>();
+
+    let table = Table::new(rows)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title("Merge Matrix"))
+        .widths(&widths);
+    f.render_widget(table, rects[0]);
+    f.render_widget(Paragraph::new(notes), rects[1]);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -211,11 +297,13 @@ mod tests {
                 status: MergeAnalysisStatus::UpToDate,
                 from_branch: "feature".to_string(),
                 to_branch: "master".to_string(),
+                ..Default::default()
             },
             MergeAnalysisResult {
                 status: MergeAnalysisStatus::FastForward,
                 from_branch: "master".to_string(),
                 to_branch: "feature".to_string(),
+                ..Default::default()
             },
         ]);
         assert_eq!(app.state.selected(), None);
@@ -244,6 +332,38 @@ mod tests {
         assert_eq!(app.state.selected(), None);
     }
 
+    #[test]
+    fn test_app_default_branch_first() {
+        let app = App::new(vec![
+            MergeAnalysisResult {
+                from_branch: "feature".to_string(),
+                to_branch: "other".to_string(),
+                ..Default::default()
+            },
+            MergeAnalysisResult {
+                from_branch: "other".to_string(),
+                to_branch: "main".to_string(),
+                to_default: true,
+                ..Default::default()
+            },
+            MergeAnalysisResult {
+                from_branch: "main".to_string(),
+                to_branch: "feature".to_string(),
+                from_default: true,
+                ..Default::default()
+            },
+        ]);
+        let names: Vec<_> = app
+            .items
+            .iter()
+            .map(|item| (item.from_branch.as_str(), item.to_branch.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![("other", "main"), ("main", "feature"), ("feature", "other")]
+        );
+    }
+
     #[test]
     fn test_ui() {
         let mut app = App::new(vec![
@@ -251,11 +371,13 @@ mod tests {
                 status: MergeAnalysisStatus::UpToDate,
                 from_branch: "feature".to_string(),
                 to_branch: "master".to_string(),
+                ..Default::default()
             },
             MergeAnalysisResult {
                 status: MergeAnalysisStatus::FastForward,
                 from_branch: "master".to_string(),
                 to_branch: "feature".to_string(),
+                ..Default::default()
             },
         ]);
 
@@ -321,4 +443,53 @@ mod tests {
 
         terminal.backend().assert_buffer(&expected);
     }
+
+    #[test]
+    fn test_matrix_ui() {
+        let mut app = App::new(vec![
+            MergeAnalysisResult {
+                status: MergeAnalysisStatus::UpToDate,
+                from_branch: "feature".to_string(),
+                to_branch: "master".to_string(),
+                ..Default::default()
+            },
+            MergeAnalysisResult {
+                status: MergeAnalysisStatus::FastForward,
+                from_branch: "master".to_string(),
+                to_branch: "feature".to_string(),
+                ..Default::default()
+            },
+        ]);
+        app.matrix = true;
+
+        let backend = TestBackend::new(40, 9);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|f| ui(f, &mut app))
+            .expect("Failed to draw UI");
+
+        let buffer = terminal.backend().buffer();
+        let lines = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol.as_str())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        // cells after emoji are left blank, as emoji take two of them
+        assert_eq!(
+            lines,
+            vec![
+                "                                        ",
+                " ┌Merge Matrix────────────────────────┐ ",
+                " │from \\ into feature master          │ ",
+                " │feature     -       ✅ ✅             │ ",
+                " │master      🚀 ✅     -               │ ",
+                " └────────────────────────────────────┘ ",
+                " ✅ ✅  no changes, already up-to-date    ",
+                " 🚀 ✅  fast-forward merge is possible    ",
+                "                                        ",
+            ]
+        );
+    }
 }
@@ -0,0 +1,451 @@
+use std::time::SystemTime;
+
+use serde::Serialize;
+
+use crate::{
+    analysis::AnalysisSink,
+    cli::CloneStrategy,
+    html::ReportMetadata,
+    result::{MergeAnalysisResult, MergeAnalysisStatus},
+};
+
+/// Version of `json` output, to be increased whenever its shape changes in
+/// a way that existing consumers could not read
+pub(crate) const SCHEMA_VERSION: u32 = 1;
+
+/// JSON Schema of `json` output, published alongside the sources
+#[cfg(test)]
+const SCHEMA: &str = include_str!("../schema/probranchinator.schema.json");
+
+/// Options that decided which branches were analysed and how, recorded so
+/// that archived results can be interpreted later
+#[derive(Serialize, Debug, Default)]
+pub(crate) struct RunOptions {
+    /// Branches listed explicitly, recent ones are chosen when empty
+    pub branches: Vec<String>,
+    pub recent: usize,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// Order of recent branches, as it is passed to `--sort`
+    pub sort: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    pub default_branch: bool,
+    pub pull_requests: bool,
+    /// Whether uncommitted changes of local repository were analysed
+    pub working_tree: bool,
+    pub clone: CloneStrategy,
+    pub offline: bool,
+    pub deepen_limit: u32,
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    schema_version: u32,
+    tool_version: &'static str,
+    /// URL of remote repository with credentials redacted, or path of local one
+    remote: &'a str,
+    generated_at: String,
+    /// When cached data was used without fetching it, time of the last fetch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fetched_at: Option<String>,
+    options: &'a RunOptions,
+    branches: Vec<Branch<'a>>,
+    results: &'a [MergeAnalysisResult],
+}
+
+#[derive(Serialize)]
+struct Branch<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    default: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    committed_at: Option<&'a str>,
+}
+
+/// Result as it was output before `json` output got its envelope
+#[derive(Serialize)]
+struct LegacyResult<'a> {
+    from_branch: &'a str,
+    to_branch: &'a str,
+    status: &'a MergeAnalysisStatus,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    from_default: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    to_default: bool,
+}
+
+/// Renders results with metadata of the run as a versioned JSON object
+pub(crate) fn render(
+    results: &[MergeAnalysisResult],
+    metadata: &ReportMetadata,
+    options: &RunOptions,
+    pretty: bool,
+) -> serde_json::Result<String> {
+    let envelope = Envelope {
+        schema_version: SCHEMA_VERSION,
+        tool_version: env!("CARGO_PKG_VERSION"),
+        remote: &metadata.source,
+        generated_at: time(metadata.generated_at),
+        fetched_at: metadata.fetched_at.map(time),
+        options,
+        branches: branches(results),
+        results,
+    };
+    to_string(&envelope, pretty)
+}
+
+/// Renders results as bare array with branch names and statuses only
+pub(crate) fn render_legacy(
+    results: &[MergeAnalysisResult],
+    pretty: bool,
+) -> serde_json::Result<String> {
+    let results = results
+        .iter()
+        .map(|result| LegacyResult {
+            from_branch: &result.from_branch,
+            to_branch: &result.to_branch,
+            status: &result.status,
+            from_default: result.from_default,
+            to_default: result.to_default,
+        })
+        .collect::<Vec<_>>();
+    to_string(&results, pretty)
+}
+
+fn to_string(value: &impl Serialize, pretty: bool) -> serde_json::Result<String> {
+    if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+}
+
+/// Lists branches with their tip commits, in order they appear in results
+fn branches(results: &[MergeAnalysisResult]) -> Vec<Branch<'_>> {
+    let mut branches: Vec<Branch> = Vec::new();
+    for result in results {
+        for branch in [
+            Branch {
+                name: &result.from_branch,
+                default: result.from_default,
+                commit: result.from_commit.as_deref(),
+                committed_at: result.from_committed_at.as_deref(),
+            },
+            Branch {
+                name: &result.to_branch,
+                default: result.to_default,
+                commit: result.to_commit.as_deref(),
+                committed_at: result.to_committed_at.as_deref(),
+            },
+        ] {
+            if !branches.iter().any(|known| known.name == branch.name) {
+                branches.push(branch);
+            }
+        }
+    }
+    branches
+}
+
+/// Event of analysis written as a line of `ndjson` output, told apart from
+/// results by its `type`, which results never have
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Event<'a> {
+    Started {
+        pairs: usize,
+    },
+    Analysing {
+        position: usize,
+        pairs: usize,
+        from_branch: &'a str,
+        to_branch: &'a str,
+    },
+    Finished {
+        pairs: usize,
+    },
+}
+
+/// Writes each result as a JSON line as soon as its pair of branches is
+/// analysed, with events of progress in between when they are wanted
+pub(crate) struct NdjsonSink<'a> {
+    out: &'a mut dyn std::io::Write,
+    progress: bool,
+    pairs: usize,
+    position: usize,
+}
+
+impl<'a> NdjsonSink<'a> {
+    pub fn new(out: &'a mut dyn std::io::Write, progress: bool) -> Self {
+        NdjsonSink {
+            out,
+            progress,
+            pairs: 0,
+            position: 0,
+        }
+    }
+
+    fn write_line(&mut self, value: &impl Serialize) -> eyre::Result<()> {
+        writeln!(self.out, "{}", serde_json::to_string(value)?)?;
+        // consumers read lines while analysis is still running
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn write_event(&mut self, event: &Event) -> eyre::Result<()> {
+        if self.progress {
+            self.write_line(event)?;
+        }
+        Ok(())
+    }
+}
+
+impl AnalysisSink for NdjsonSink<'_> {
+    fn started(&mut self, pairs: usize) -> eyre::Result<()> {
+        self.pairs = pairs;
+        self.write_event(&Event::Started { pairs })
+    }
+
+    fn analysing(&mut self, from_branch: &str, into_branch: &str) -> eyre::Result<()> {
+        self.position += 1;
+        self.write_event(&Event::Analysing {
+            position: self.position,
+            pairs: self.pairs,
+            from_branch,
+            to_branch: into_branch,
+        })
+    }
+
+    fn analysed(&mut self, result: &MergeAnalysisResult) -> eyre::Result<()> {
+        self.write_line(result)
+    }
+
+    fn finished(&mut self) -> eyre::Result<()> {
+        self.write_event(&Event::Finished { pairs: self.pairs })
+    }
+}
+
+fn time(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::SortOrder;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn results() -> Vec<MergeAnalysisResult> {
+        vec![
+            MergeAnalysisResult {
+                from_branch: "feature".to_string(),
+                to_branch: "main".to_string(),
+                status: MergeAnalysisStatus::Conflicts,
+                to_default: true,
+                from_commit: Some("1111111".to_string()),
+                to_commit: Some("2222222".to_string()),
+                from_committed_at: Some("2023-01-02T00:00:00Z".to_string()),
+                to_committed_at: Some("2023-01-01T00:00:00Z".to_string()),
+                ahead: Some(2),
+                behind: Some(1),
+                conflicts: vec!["src/lib.rs".to_string()],
+                ..Default::default()
+            },
+            MergeAnalysisResult {
+                from_branch: "main".to_string(),
+                to_branch: "feature".to_string(),
+                status: MergeAnalysisStatus::Normal,
+                from_default: true,
+                from_commit: Some("2222222".to_string()),
+                to_commit: Some("1111111".to_string()),
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn metadata() -> ReportMetadata {
+        ReportMetadata {
+            source: "https://example.com/repo.git".to_string(),
+            generated_at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            fetched_at: Some(UNIX_EPOCH + Duration::from_secs(1_600_000_000)),
+        }
+    }
+
+    #[test]
+    fn test_render() -> Result<(), Box<dyn std::error::Error>> {
+        let options = RunOptions {
+            recent: 10,
+            sort: SortOrder::CommitterDate.to_string(),
+            default_branch: true,
+            ..Default::default()
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&results(), &metadata(), &options, false)?)?;
+        assert_eq!(json["schema_version"], json!(SCHEMA_VERSION));
+        assert_eq!(json["tool_version"], json!(env!("CARGO_PKG_VERSION")));
+        assert_eq!(json["remote"], json!("https://example.com/repo.git"));
+        assert_eq!(json["generated_at"], json!("2023-11-14T22:13:20Z"));
+        assert_eq!(json["fetched_at"], json!("2020-09-13T12:26:40Z"));
+        assert_eq!(json["options"]["recent"], json!(10));
+        assert_eq!(json["options"]["sort"], json!("committerdate"));
+        assert_eq!(json["options"]["clone"], json!("bare"));
+        assert_eq!(
+            json["branches"],
+            json!([
+                {
+                    "name": "feature",
+                    "commit": "1111111",
+                    "committed_at": "2023-01-02T00:00:00Z"
+                },
+                {
+                    "name": "main",
+                    "default": true,
+                    "commit": "2222222",
+                    "committed_at": "2023-01-01T00:00:00Z"
+                }
+            ])
+        );
+        assert_eq!(json["results"].as_array().unwrap().len(), 2);
+        assert_eq!(json["results"][0]["conflicts"], json!(["src/lib.rs"]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_legacy() -> Result<(), Box<dyn std::error::Error>> {
+        let json: serde_json::Value = serde_json::from_str(&render_legacy(&results(), true)?)?;
+        assert_eq!(
+            json,
+            json!([
+                {
+                    "from_branch": "feature",
+                    "to_branch": "main",
+                    "status": "Conflicts",
+                    "to_default": true
+                },
+                {
+                    "from_branch": "main",
+                    "to_branch": "feature",
+                    "status": "Normal",
+                    "from_default": true
+                }
+            ])
+        );
+        Ok(())
+    }
+
+    fn stream(progress: bool) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error>> {
+        let mut buf = Vec::new();
+        let mut sink = NdjsonSink::new(&mut buf, progress);
+        sink.started(2)?;
+        for result in results() {
+            sink.analysing(&result.from_branch, &result.to_branch)?;
+            sink.analysed(&result)?;
+        }
+        sink.finished()?;
+        let lines = String::from_utf8(buf)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(lines)
+    }
+
+    #[test]
+    fn test_ndjson_sink() -> Result<(), Box<dyn std::error::Error>> {
+        let lines = stream(false)?;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["from_branch"], json!("feature"));
+        assert_eq!(lines[0]["status"], json!("Conflicts"));
+        assert_eq!(lines[1]["from_branch"], json!("main"));
+
+        // results are read back as they were analysed
+        let result: MergeAnalysisResult = serde_json::from_value(lines[0].clone())?;
+        assert_eq!(result.conflicts, vec!["src/lib.rs".to_string()]);
+
+        let lines = stream(true)?;
+        let types = lines
+            .iter()
+            .map(|line| line["type"].as_str().unwrap_or("result"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                "started",
+                "analysing",
+                "result",
+                "analysing",
+                "result",
+                "finished"
+            ]
+        );
+        assert_eq!(
+            lines[3],
+            json!({
+                "type": "analysing",
+                "position": 2,
+                "pairs": 2,
+                "from_branch": "main",
+                "to_branch": "feature"
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_matches_output() -> Result<(), Box<dyn std::error::Error>> {
+        let schema: serde_json::Value = serde_json::from_str(SCHEMA)?;
+        assert_eq!(
+            schema["properties"]["schema_version"]["const"],
+            json!(SCHEMA_VERSION)
+        );
+
+        // every property that is output is described by the schema
+        let json: serde_json::Value = serde_json::from_str(&render(
+            &results(),
+            &metadata(),
+            &RunOptions {
+                since: Some("1day".to_string()),
+                until: Some("1h".to_string()),
+                author: Some("someone".to_string()),
+                base: Some("main".to_string()),
+                ..Default::default()
+            },
+            false,
+        )?)?;
+        let described = |schema: &serde_json::Value, value: &serde_json::Value| {
+            for key in value.as_object().unwrap().keys() {
+                assert!(
+                    schema["properties"].get(key).is_some(),
+                    "{} is not in schema",
+                    key
+                );
+            }
+            for required in schema["required"].as_array().unwrap() {
+                assert!(
+                    value.get(required.as_str().unwrap()).is_some(),
+                    "{} is required by schema",
+                    required
+                );
+            }
+        };
+        let definitions = &schema["$defs"];
+        described(&schema, &json);
+        described(&definitions["options"], &json["options"]);
+        for branch in json["branches"].as_array().unwrap() {
+            described(&definitions["branch"], branch);
+        }
+        for result in json["results"].as_array().unwrap() {
+            described(&definitions["result"], result);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use std::fmt::Write;
+
+use crate::{
+    html::{escape, write_string},
+    result::{MergeAnalysisResult, MergeAnalysisStatus},
+};
+
+/// How result of a merge is reported as test case
+#[derive(PartialEq, Eq)]
+enum Outcome {
+    Passed,
+    Failed,
+    /// Analysis could not tell whether merge is possible
+    Skipped,
+}
+
+fn outcome(status: &MergeAnalysisStatus) -> Outcome {
+    match status {
+        MergeAnalysisStatus::UpToDate
+        | MergeAnalysisStatus::FastForward
+        | MergeAnalysisStatus::Normal => Outcome::Passed,
+        MergeAnalysisStatus::Conflicts
+        | MergeAnalysisStatus::None
+        | MergeAnalysisStatus::Error { .. } => Outcome::Failed,
+        MergeAnalysisStatus::Unknown | MergeAnalysisStatus::BeyondShallowDepth => Outcome::Skipped,
+    }
+}
+
+/// Renders results as JUnit XML, with a test case for each pair of branches
+/// grouped into test suites by branch being merged into, so that merges
+/// that are not possible are shown as failed tests
+pub(crate) fn render(results: &[MergeAnalysisResult]) -> String {
+    write_string(|xml| write_report(xml, results))
+}
+
+fn write_report(xml: &mut String, results: &[MergeAnalysisResult]) -> std::fmt::Result {
+    let mut targets: Vec<&str> = Vec::new();
+    for result in results {
+        if !targets.contains(&result.to_branch.as_str()) {
+            targets.push(&result.to_branch);
+        }
+    }
+
+    writeln!(xml, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+        xml,
+        "<testsuites name=\"probranchinator\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">",
+        results.len(),
+        count(results, Outcome::Failed),
+        count(results, Outcome::Skipped)
+    )?;
+    for target in targets {
+        let suite = results
+            .iter()
+            .filter(|result| result.to_branch == target)
+            .collect::<Vec<_>>();
+        writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">",
+            escape(&suite[0].display_to_branch()),
+            suite.len(),
+            count(suite.iter().copied(), Outcome::Failed),
+            count(suite.iter().copied(), Outcome::Skipped)
+        )?;
+        for result in suite {
+            write_test_case(xml, result)?;
+        }
+        writeln!(xml, "  </testsuite>")?;
+    }
+    writeln!(xml, "</testsuites>")
+}
+
+fn write_test_case(xml: &mut String, result: &MergeAnalysisResult) -> std::fmt::Result {
+    // plain branch names keep test cases the same when default branch changes,
+    // which is only marked in name of test suite
+    write!(
+        xml,
+        "    <testcase classname=\"{}\" name=\"{} -&gt; {}\"",
+        escape(&result.to_branch),
+        escape(&result.from_branch),
+        escape(&result.to_branch)
+    )?;
+    match outcome(&result.status) {
+        Outcome::Passed => writeln!(xml, "/>"),
+        Outcome::Skipped => {
+            writeln!(xml, ">")?;
+            writeln!(
+                xml,
+                "      <skipped message=\"{}\"/>",
+                escape(&result.status.to_string())
+            )?;
+            writeln!(xml, "    </testcase>")
+        }
+        Outcome::Failed => {
+            let mut message = result.status.to_string();
+            if !result.conflicts.is_empty() {
+                message = format!(
+                    "{} Conflicting files: {}",
+                    message,
+                    result.conflicts.join(", ")
+                );
+            }
+            writeln!(xml, ">")?;
+            write!(
+                xml,
+                "      <failure type=\"{}\" message=\"{}\">",
+                result.status.code(),
+                escape(&message)
+            )?;
+            // body lists conflicting files one per line, for longer lists
+            for path in &result.conflicts {
+                writeln!(xml, "{}", escape(path))?;
+            }
+            writeln!(xml, "</failure>")?;
+            writeln!(xml, "    </testcase>")
+        }
+    }
+}
+
+fn count<'a>(results: impl IntoIterator<Item = &'a MergeAnalysisResult>, kind: Outcome) -> usize {
+    results
+        .into_iter()
+        .filter(|result| outcome(&result.status) == kind)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_render() {
+        let results = vec![
+            MergeAnalysisResult {
+                from_branch: "feature".to_string(),
+                to_branch: "main".to_string(),
+                status: MergeAnalysisStatus::Conflicts,
+                to_default: true,
+                conflicts: vec!["src/a.rs".to_string(), "src/<b>.rs".to_string()],
+                ..Default::default()
+            },
+            MergeAnalysisResult {
+                from_branch: "main".to_string(),
+                to_branch: "feature".to_string(),
+                status: MergeAnalysisStatus::FastForward,
+                from_default: true,
+                ..Default::default()
+            },
+            MergeAnalysisResult {
+                from_branch: "other".to_string(),
+                to_branch: "main".to_string(),
+                status: MergeAnalysisStatus::BeyondShallowDepth,
+                to_default: true,
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            render(&results),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="probranchinator" tests="3" failures="1" skipped="1">
+  <testsuite name="main (default)" tests="2" failures="1" skipped="1">
+    <testcase classname="main" name="feature -&gt; main">
+      <failure type="conflicts" message="🚧🔧 Found conflicts, have to resolve them manually. Conflicting files: src/a.rs, src/&lt;b&gt;.rs">src/a.rs
+src/&lt;b&gt;.rs
+</failure>
+    </testcase>
+    <testcase classname="main" name="other -&gt; main">
+      <skipped message="🌊🤔 Merge base is beyond shallow depth, deepen history to analyse."/>
+    </testcase>
+  </testsuite>
+  <testsuite name="feature" tests="1" failures="0" skipped="0">
+    <testcase classname="feature" name="main -&gt; feature"/>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use eyre::{Context, Result};
+
+use crate::cache::sibling_path;
+
+/// How often lock is tried again while another process holds it
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Whether lock can be held by several processes at once
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LockMode {
+    /// For reading cached clone, e.g. during analysis
+    Shared,
+    /// For changing cached clone, e.g. cloning, fetching or removing it
+    Exclusive,
+}
+
+/// Advisory lock of cached clone, released when dropped
+///
+/// Lock is held on `<clone>.lock` file next to the clone, which is never
+/// removed, so that processes waiting for it and processes coming later
+/// always lock the same file.
+///
+/// Exclusive lock is needed for changes of references, i.e. cloning,
+/// fetching or removing the clone. Objects that partial clones lack are
+/// fetched during analysis under shared lock: such fetches do not update
+/// any reference, and git writes fetched packs under temporary names and
+/// renames them into place, so other processes only ever see complete
+/// objects, the same as with git fetching them on its own in partial clones.
+#[derive(Debug)]
+pub(crate) struct CacheLock {
+    file: File,
+    clone_path: PathBuf,
+}
+
+impl CacheLock {
+    /// Waits up to timeout until lock of cached clone is acquired
+    pub(crate) fn acquire(clone_path: &Path, mode: LockMode, timeout: Duration) -> Result<Self> {
+        let path = lock_path(clone_path);
+        let file = open(&path)?;
+        let started = Instant::now();
+        let mut waiting = false;
+        loop {
+            match try_lock(&file, mode) {
+                Ok(()) => {
+                    return Ok(CacheLock {
+                        file,
+                        clone_path: clone_path.to_owned(),
+                    })
+                }
+                Err(TryLockError::WouldBlock) if started.elapsed() < timeout => {
+                    if !waiting {
+                        log::warn!(
+                            "Cached clone {:?} is used by another process, waiting for it",
+                            clone_path
+                        );
+                        waiting = true;
+                    }
+                    std::thread::sleep(RETRY_INTERVAL);
+                }
+                Err(TryLockError::WouldBlock) => eyre::bail!(
+                    "Timed out after {} waiting for another process using cached clone {:?}, use --lock-timeout to wait longer",
+                    humantime::format_duration(timeout),
+                    clone_path
+                ),
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| format!("Failed to lock {:?}", path))
+                }
+            }
+        }
+    }
+
+    /// Acquires exclusive lock if no other process holds any lock of the clone
+    pub(crate) fn try_exclusive(clone_path: &Path) -> Result<Option<Self>> {
+        let path = lock_path(clone_path);
+        let file = open(&path)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(CacheLock {
+                file,
+                clone_path: clone_path.to_owned(),
+            })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => {
+                Err(e).with_context(|| format!("Failed to lock {:?}", path))
+            }
+        }
+    }
+
+    /// Turns exclusive lock into shared one, letting other processes
+    /// read the clone too, but not change it
+    ///
+    /// File locks cannot be downgraded atomically, so exclusive lock is
+    /// released before shared one is acquired. Another process waiting for
+    /// exclusive lock might get it in between and fetch, clone again or
+    /// remove the clone before this one reads it, so clone has to be opened
+    /// only after it is downgraded.
+    pub(crate) fn downgrade(self, timeout: Duration) -> Result<Self> {
+        self.file
+            .unlock()
+            .with_context(|| format!("Failed to unlock {:?}", lock_path(&self.clone_path)))?;
+        CacheLock::acquire(&self.clone_path, LockMode::Shared, timeout)
+    }
+}
+
+pub(crate) fn lock_path(clone_path: &Path) -> PathBuf {
+    sibling_path(clone_path, ".lock")
+}
+
+fn open(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to open lock file {:?}", path))
+}
+
+fn try_lock(file: &File, mode: LockMode) -> Result<(), TryLockError> {
+    match mode {
+        LockMode::Shared => file.try_lock_shared(),
+        LockMode::Exclusive => file.try_lock(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_lock() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let clone_path = dir.path().join("github.com_org_repo-0123456789abcdef");
+        let no_wait = Duration::ZERO;
+
+        // shared locks do not block each other, but block exclusive one
+        let shared = CacheLock::acquire(&clone_path, LockMode::Shared, no_wait)?;
+        let other = CacheLock::acquire(&clone_path, LockMode::Shared, no_wait)?;
+        assert!(CacheLock::try_exclusive(&clone_path)?.is_none());
+        let error = CacheLock::acquire(&clone_path, LockMode::Exclusive, no_wait).unwrap_err();
+        assert!(error.to_string().contains("--lock-timeout"));
+        drop((shared, other));
+
+        // exclusive lock blocks all others until it is downgraded
+        let exclusive = CacheLock::acquire(&clone_path, LockMode::Exclusive, no_wait)?;
+        assert!(CacheLock::acquire(&clone_path, LockMode::Shared, no_wait).is_err());
+        let shared = exclusive.downgrade(no_wait)?;
+        assert!(CacheLock::acquire(&clone_path, LockMode::Shared, no_wait).is_ok());
+        drop(shared);
+
+        assert!(CacheLock::try_exclusive(&clone_path)?.is_some());
+        assert!(lock_path(&clone_path).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_downgrade_with_waiting_writer() -> Result<()> {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let dir = tempfile::tempdir()?;
+        let clone_path = dir.path().join("github.com_org_repo-0123456789abcdef");
+        let timeout = Duration::from_secs(5);
+
+        // another holder waits to change the clone while it is fetched
+        let exclusive = CacheLock::acquire(&clone_path, LockMode::Exclusive, Duration::ZERO)?;
+        let writing = Arc::new(AtomicBool::new(false));
+        let writer = std::thread::spawn({
+            let (clone_path, writing) = (clone_path.clone(), writing.clone());
+            move || -> Result<()> {
+                let lock = CacheLock::acquire(&clone_path, LockMode::Exclusive, timeout)?;
+                writing.store(true, Ordering::SeqCst);
+                std::thread::sleep(RETRY_INTERVAL * 2);
+                writing.store(false, Ordering::SeqCst);
+                drop(lock);
+                Ok(())
+            }
+        });
+        std::thread::sleep(RETRY_INTERVAL);
+
+        // writer might take over while lock is downgraded, but it is done
+        // before shared lock is acquired and cannot start while it is held
+        let shared = exclusive.downgrade(timeout)?;
+        assert!(!writing.load(Ordering::SeqCst));
+        std::thread::sleep(RETRY_INTERVAL * 3);
+        assert!(!writing.load(Ordering::SeqCst));
+        drop(shared);
+
+        writer.join().unwrap()?;
+        Ok(())
+    }
+}
@@ -1,24 +1,50 @@
 mod analysis;
+mod cache;
 mod cli;
 mod clone;
+mod config;
+mod git;
+mod graph;
+mod groups;
+mod html;
 mod interactive;
+mod json;
+mod junit;
+mod lock;
+mod matrix;
+mod native;
+mod partial;
+mod pattern;
 mod probranchinator;
+mod pull_request;
 mod recent;
 mod repo;
 mod result;
+mod shallow;
+mod tabular;
 
-use clap::Parser;
+/// Exit code when remote repository could not be reached to clone or fetch it,
+/// so that network failures can be told apart from failed analysis
+const NETWORK_FAILURE_EXIT_CODE: i32 = 3;
 
 fn main() -> eyre::Result<()> {
-    env_logger::init();
-    let args = cli::Args::parse();
+    // warnings are shown by default, as they are meant for user
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    let args = cli::Args::parse_checked();
     let probranchinator = Probranchinator {};
-    return probranchinator::run_probranchinator(
+    let result = probranchinator::run_probranchinator(
         args,
         &mut std::io::stdout(),
         &probranchinator,
         &probranchinator,
     );
+    if let Err(e) = &result {
+        if git::NetworkError::is_cause_of(e) {
+            eprintln!("Error: {:?}", e);
+            std::process::exit(NETWORK_FAILURE_EXIT_CODE);
+        }
+    }
+    result
 }
 
 pub(crate) struct Probranchinator {}
@@ -26,6 +52,7 @@ pub(crate) struct Probranchinator {}
 #[cfg(test)]
 mod tests {
     mod analysis_test;
+    mod cache_test;
     mod recent_test;
     mod repo_test;
     mod support;
@@ -0,0 +1,293 @@
+use tabled::builder::Builder;
+
+use crate::result::MergeAnalysisResult;
+
+/// Branch names longer than this are abbreviated and listed under the matrix
+const MAX_NAME_WIDTH: usize = 20;
+
+/// Meaning of each short status, in order it is listed in legend
+const LEGEND: [(&str, &str); 7] = [
+    ("✅✅", "no changes, already up-to-date"),
+    ("🚀✅", "fast-forward merge is possible"),
+    ("🤝✅", "automatic merge is possible"),
+    ("🚧🔧", "conflicts have to be resolved manually"),
+    ("❌❌", "no merge is possible"),
+    ("❌🤔", "unknown merge analysis result"),
+    ("🌊🤔", "merge base is beyond shallow depth"),
+];
+
+/// Lists branches in order they first appear in results, which is order
+/// of analysis, along with whether they are the default one
+pub(crate) fn branches(results: &[MergeAnalysisResult]) -> Vec<(&str, bool)> {
+    let mut branches: Vec<(&str, bool)> = Vec::new();
+    for result in results {
+        for (name, default) in [
+            (&result.from_branch, result.from_default),
+            (&result.to_branch, result.to_default),
+        ] {
+            if !branches.iter().any(|(known, _)| *known == name) {
+                branches.push((name, default));
+            }
+        }
+    }
+    branches
+}
+
+/// How matrix is rendered as text
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MatrixStyle {
+    Table,
+    Markdown,
+}
+
+/// Cell of matrix, for a pair of branches
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum MatrixCell<'a> {
+    /// Branch would be merged into itself
+    SameBranch,
+    /// Pair of branches was not analysed
+    Missing,
+    Result(&'a MergeAnalysisResult),
+}
+
+impl MatrixCell<'_> {
+    /// Short status shown in the cell
+    pub(crate) fn short(&self) -> &'static str {
+        match self {
+            MatrixCell::SameBranch => "-",
+            MatrixCell::Missing => "",
+            MatrixCell::Result(result) => result.status.emoji(),
+        }
+    }
+}
+
+/// Results arranged so that rows are branches being merged
+/// and columns are branches being merged into
+#[derive(Debug)]
+pub(crate) struct Matrix<'a> {
+    /// Labels of branches being merged
+    pub rows: Vec<String>,
+    /// Labels of branches being merged into
+    pub columns: Vec<String>,
+    pub cells: Vec<Vec<MatrixCell<'a>>>,
+    /// Full names of abbreviated branches with their labels
+    pub footnotes: Vec<(String, String)>,
+}
+
+impl<'a> Matrix<'a> {
+    /// Arranges results into matrix, keeping order in which branches
+    /// first appear in results
+    pub(crate) fn new(results: &'a [MergeAnalysisResult]) -> Matrix<'a> {
+        let branches = branches(results);
+
+        let mut footnotes = Vec::new();
+        let labels = branches
+            .iter()
+            .map(|(name, default)| {
+                let label = if name.chars().count() > MAX_NAME_WIDTH {
+                    let marker = format!("…[{}]", footnotes.len() + 1);
+                    let kept = MAX_NAME_WIDTH - marker.chars().count();
+                    let label = name.chars().take(kept).collect::<String>() + &marker;
+                    footnotes.push((label.clone(), name.to_string()));
+                    label
+                } else {
+                    name.to_string()
+                };
+                if *default {
+                    format!("{} (default)", label)
+                } else {
+                    label
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // working tree is only merged, and other branches are only merged into
+        let is_from = |name: &str| results.iter().any(|result| result.from_branch == name);
+        let is_to = |name: &str| results.iter().any(|result| result.to_branch == name);
+        let from = (0..branches.len())
+            .filter(|&i| is_from(branches[i].0))
+            .collect::<Vec<_>>();
+        let into = (0..branches.len())
+            .filter(|&i| is_to(branches[i].0))
+            .collect::<Vec<_>>();
+
+        let cells = from
+            .iter()
+            .map(|&row| {
+                into.iter()
+                    .map(|&column| {
+                        if row == column {
+                            return MatrixCell::SameBranch;
+                        }
+                        results
+                            .iter()
+                            .find(|result| {
+                                result.from_branch == branches[row].0
+                                    && result.to_branch == branches[column].0
+                            })
+                            .map_or(MatrixCell::Missing, MatrixCell::Result)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Matrix {
+            rows: from.iter().map(|&i| labels[i].clone()).collect(),
+            columns: into.iter().map(|&i| labels[i].clone()).collect(),
+            cells,
+            footnotes,
+        }
+    }
+
+    /// Meaning of short statuses that appear in the matrix
+    pub(crate) fn legend(&self) -> Vec<String> {
+        LEGEND
+            .iter()
+            .filter(|(emoji, _)| {
+                self.cells
+                    .iter()
+                    .flatten()
+                    .any(|cell| cell.short() == *emoji)
+            })
+            .map(|(emoji, meaning)| format!("{} {}", emoji, meaning))
+            .collect()
+    }
+
+    /// Full names of abbreviated branches
+    pub(crate) fn footnote_lines(&self) -> Vec<String> {
+        self.footnotes
+            .iter()
+            .map(|(label, name)| format!("{} {}", label, name))
+            .collect()
+    }
+
+    /// Renders matrix as table followed by legend and full names of
+    /// abbreviated branches
+    pub(crate) fn render(&self, style: MatrixStyle) -> String {
+        let mut builder = Builder::default();
+        builder.set_header(
+            std::iter::once("from \\ into".to_owned()).chain(self.columns.iter().cloned()),
+        );
+        for (label, row) in self.rows.iter().zip(&self.cells) {
+            builder.push_record(
+                std::iter::once(label.clone())
+                    .chain(row.iter().map(|cell| cell.short().to_owned())),
+            );
+        }
+        let mut table = builder.build();
+        if style == MatrixStyle::Markdown {
+            table.with(tabled::settings::Style::markdown());
+        }
+
+        let mut text = table.to_string();
+        text.push('\n');
+        for lines in [self.legend(), self.footnote_lines()] {
+            if lines.is_empty() {
+                continue;
+            }
+            text.push('\n');
+            for line in lines {
+                // separate lines would be joined into one paragraph in markdown
+                if style == MatrixStyle::Markdown {
+                    text.push_str("- ");
+                }
+                text.push_str(&line);
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::MergeAnalysisStatus;
+    use pretty_assertions::assert_eq;
+
+    fn result(from: &str, to: &str, status: MergeAnalysisStatus) -> MergeAnalysisResult {
+        MergeAnalysisResult {
+            from_branch: from.to_string(),
+            to_branch: to.to_string(),
+            status,
+            from_default: from == "main",
+            to_default: to == "main",
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_matrix() {
+        let results = vec![
+            result("main", "feature", MergeAnalysisStatus::FastForward),
+            result(
+                "main",
+                "feature/with-very-long-name",
+                MergeAnalysisStatus::Normal,
+            ),
+            result("feature", "main", MergeAnalysisStatus::UpToDate),
+            result(
+                "feature",
+                "feature/with-very-long-name",
+                MergeAnalysisStatus::Conflicts,
+            ),
+            result(
+                "feature/with-very-long-name",
+                "main",
+                MergeAnalysisStatus::Normal,
+            ),
+            result(
+                "feature/with-very-long-name",
+                "feature",
+                MergeAnalysisStatus::Error {
+                    message: "failed".to_owned(),
+                },
+            ),
+        ];
+        let matrix = Matrix::new(&results);
+        assert_eq!(
+            matrix.render(MatrixStyle::Table),
+            r#"
++----------------------+----------------+---------+----------------------+
+| from \ into          | main (default) | feature | feature/with-ver…[1] |
++----------------------+----------------+---------+----------------------+
+| main (default)       | -              | 🚀✅    | 🤝✅                 |
++----------------------+----------------+---------+----------------------+
+| feature              | ✅✅           | -       | 🚧🔧                 |
++----------------------+----------------+---------+----------------------+
+| feature/with-ver…[1] | 🤝✅           | ❌❌    | -                    |
++----------------------+----------------+---------+----------------------+
+
+✅✅ no changes, already up-to-date
+🚀✅ fast-forward merge is possible
+🤝✅ automatic merge is possible
+🚧🔧 conflicts have to be resolved manually
+❌❌ no merge is possible
+
+feature/with-ver…[1] feature/with-very-long-name
+"#
+            .trim_start()
+        );
+    }
+
+    #[test]
+    fn test_matrix_markdown() {
+        let results = vec![
+            result("(working tree)", "main", MergeAnalysisStatus::Normal),
+            result("(working tree)", "feature", MergeAnalysisStatus::Conflicts),
+        ];
+        // working tree is only merged, so it is the only row
+        assert_eq!(
+            Matrix::new(&results).render(MatrixStyle::Markdown),
+            r#"
+| from \ into    | main (default) | feature |
+|----------------|----------------|---------|
+| (working tree) | 🤝✅           | 🚧🔧    |
+
+- 🤝✅ automatic merge is possible
+- 🚧🔧 conflicts have to be resolved manually
+"#
+            .trim_start()
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use std::cell::RefCell;
+use std::path::Path;
+
+use eyre::{Context, Result};
+use git2::build::RepoBuilder;
+use git2::{
+    AutotagOption, Cred, CredentialType, Direction, FetchOptions, FetchPrune, RemoteCallbacks,
+    Repository,
+};
+use indicatif::ProgressBar;
+
+use crate::cli::CloneStrategy;
+use crate::git::redact;
+
+/// Environment variable with token used to authenticate over https
+pub(crate) const TOKEN_VARIABLE: &str = "PROBRANCHINATOR_GIT_TOKEN";
+
+/// Clones repository with libgit2, without git command line tool
+pub(crate) fn clone_repo(
+    repo_url: &str,
+    dst: &Path,
+    strategy: CloneStrategy,
+    progress: Option<&ProgressBar>,
+) -> Result<()> {
+    check_strategy(strategy)?;
+    match strategy {
+        CloneStrategy::Full => {
+            RepoBuilder::new()
+                .fetch_options(fetch_options(progress))
+                .clone(repo_url, dst)
+                .with_context(|| format!("Failed to clone repository {}", redact(repo_url)))?;
+        }
+        CloneStrategy::Bare => {
+            let repo = Repository::init_bare(dst)
+                .with_context(|| format!("Failed to create repository in {:?}", dst))?;
+            let mut remote = repo.remote("origin", repo_url)?;
+
+            // origin/HEAD points to default branch of origin,
+            // which is only known while connected to it
+            let default_branch = {
+                let connection = remote
+                    .connect_auth(Direction::Fetch, Some(callbacks(None)), None)
+                    .with_context(|| format!("Failed to connect to {}", redact(repo_url)))?;
+                connection
+                    .default_branch()
+                    .ok()
+                    .and_then(|name| name.as_str().map(str::to_owned))
+            };
+
+            remote
+                .fetch(&[] as &[&str], Some(&mut fetch_options(progress)), None)
+                .with_context(|| format!("Failed to fetch repository {}", redact(repo_url)))?;
+
+            match default_branch
+                .as_deref()
+                .and_then(|name| name.strip_prefix("refs/heads/"))
+            {
+                Some(name) => {
+                    repo.reference_symbolic(
+                        "refs/remotes/origin/HEAD",
+                        &format!("refs/remotes/origin/{}", name),
+                        true,
+                        "set origin/HEAD to default branch",
+                    )?;
+                }
+                None => log::warn!("Default branch of {} is not known", redact(repo_url)),
+            }
+        }
+        CloneStrategy::Blobless | CloneStrategy::Treeless => {
+            eyre::bail!("--clone={} requires the git CLI transport", strategy)
+        }
+    }
+    Ok(())
+}
+
+/// Fails for clones libgit2 cannot make, which are not worth retrying
+pub(crate) fn check_strategy(strategy: CloneStrategy) -> Result<()> {
+    if let CloneStrategy::Blobless | CloneStrategy::Treeless = strategy {
+        eyre::bail!(
+            "--clone={} is not supported by libgit2, use --transport=cli instead",
+            strategy
+        )
+    }
+    Ok(())
+}
+
+/// Fetches latest changes from origin and prunes branches deleted there
+pub(crate) fn fetch(path: &Path, progress: Option<&ProgressBar>) -> Result<()> {
+    let repo = Repository::open(path)
+        .with_context(|| format!("Failed to open repository in directory {:?}", path))?;
+    let mut remote = repo.find_remote("origin")?;
+    let mut options = fetch_options(progress);
+    options.prune(FetchPrune::On);
+    remote
+        .fetch(&[] as &[&str], Some(&mut options), None)
+        .context("Failed to fetch repository")?;
+    Ok(())
+}
+
+fn fetch_options(progress: Option<&ProgressBar>) -> FetchOptions<'_> {
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(callbacks(progress));
+    options.download_tags(AutotagOption::None);
+    options
+}
+
+fn callbacks(progress: Option<&ProgressBar>) -> RemoteCallbacks<'_> {
+    let mut callbacks = RemoteCallbacks::new();
+
+    // libgit2 keeps asking for credentials while they are rejected,
+    // so each kind of them is only tried once
+    let tried = RefCell::new(CredentialType::empty());
+    callbacks.credentials(move |url, username_from_url, allowed| {
+        let mut tried = tried.borrow_mut();
+        let username = username_from_url.unwrap_or("git");
+
+        if allowed.contains(CredentialType::SSH_KEY) && !tried.contains(CredentialType::SSH_KEY) {
+            tried.insert(CredentialType::SSH_KEY);
+            return Cred::ssh_key_from_agent(username);
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT)
+            && !tried.contains(CredentialType::USER_PASS_PLAINTEXT)
+        {
+            tried.insert(CredentialType::USER_PASS_PLAINTEXT);
+            if let Ok(token) = std::env::var(TOKEN_VARIABLE) {
+                // hosting services accept tokens as password with any username
+                return Cred::userpass_plaintext(
+                    username_from_url.unwrap_or("x-access-token"),
+                    &token,
+                );
+            }
+            return git2::Config::open_default()
+                .and_then(|config| Cred::credential_helper(&config, url, username_from_url));
+        }
+        if allowed.contains(CredentialType::DEFAULT) && !tried.contains(CredentialType::DEFAULT) {
+            tried.insert(CredentialType::DEFAULT);
+            return Cred::default();
+        }
+        Err(git2::Error::from_str(&format!(
+            "no credentials accepted for {}, set {} or configure credential helper or SSH agent",
+            redact(url),
+            TOKEN_VARIABLE
+        )))
+    });
+
+    if let Some(progress) = progress {
+        callbacks.transfer_progress(|stats| {
+            progress.set_message(format!(
+                "Retrieving repository... {}/{} objects",
+                stats.received_objects(),
+                stats.total_objects()
+            ));
+            true
+        });
+    }
+
+    callbacks
+}
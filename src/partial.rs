@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+
+use eyre::{Context, Result};
+use git2::{Commit, Delta, Diff, ErrorClass, ErrorCode, Oid, Repository};
+
+use crate::git::GitSettings;
+
+/// How many times merge is retried after fetching objects it was missing
+const MAX_MISSING_OBJECT_RETRIES: usize = 100;
+
+/// Checks whether repository is a partial clone, which lacks some of
+/// trees or blobs until they are fetched from origin
+pub(crate) fn is_partial(repo: &Repository) -> bool {
+    repo.config()
+        .and_then(|config| config.get_string("extensions.partialclone"))
+        .is_ok()
+}
+
+/// Fetches objects that merge of two commits needs, but partial clone lacks
+///
+/// libgit2 cannot fetch missing objects on its own, so trees of all three
+/// commits and blobs of files changed on both sides are fetched at once
+/// with git command line tool. Does nothing for regular repositories.
+pub(crate) fn prefetch_for_merge(
+    repo: &Repository,
+    git: &GitSettings,
+    ancestor: &Commit,
+    ours: &Commit,
+    theirs: &Commit,
+) -> Result<()> {
+    if !is_partial(repo) {
+        return Ok(());
+    }
+
+    // fetching a tree brings all of its subtrees too
+    fetch_missing(
+        repo,
+        git,
+        [ancestor.tree_id(), ours.tree_id(), theirs.tree_id()],
+    )?;
+
+    let ancestor_tree = ancestor.tree()?;
+    let our_diff = repo.diff_tree_to_tree(Some(&ancestor_tree), Some(&ours.tree()?), None)?;
+    let their_diff = repo.diff_tree_to_tree(Some(&ancestor_tree), Some(&theirs.tree()?), None)?;
+
+    // only files changed on both sides have to be merged by content
+    let our_paths = changed_paths(&our_diff);
+    let their_paths = changed_paths(&their_diff);
+    let conflicting = our_paths.intersection(&their_paths).collect::<HashSet<_>>();
+    let blobs = our_diff
+        .deltas()
+        .chain(their_diff.deltas())
+        .filter(|delta| {
+            [delta.old_file().path(), delta.new_file().path()]
+                .into_iter()
+                .flatten()
+                .any(|path| conflicting.contains(&path.to_string_lossy().into_owned()))
+        })
+        .flat_map(|delta| [delta.old_file().id(), delta.new_file().id()])
+        .filter(|oid| !oid.is_zero())
+        .collect::<Vec<_>>();
+    fetch_missing(repo, git, blobs)
+}
+
+/// Runs operation on partial clone, fetching objects it reports missing
+///
+/// Covers objects that [`prefetch_for_merge`] could not predict,
+/// e.g. blobs read by rename detection.
+pub(crate) fn retry_missing<T>(
+    repo: &Repository,
+    git: &GitSettings,
+    mut operation: impl FnMut() -> Result<T, git2::Error>,
+) -> Result<T> {
+    let mut retries = 0;
+    loop {
+        match operation() {
+            Err(e) if retries < MAX_MISSING_OBJECT_RETRIES && is_partial(repo) => {
+                match missing_object(&e) {
+                    Some(oid) => fetch_missing(repo, git, [oid])?,
+                    None => return Err(e.into()),
+                }
+                retries += 1;
+            }
+            result => return Ok(result?),
+        }
+    }
+}
+
+fn changed_paths(diff: &Diff) -> HashSet<String> {
+    diff.deltas()
+        .filter(|delta| delta.status() != Delta::Unmodified)
+        .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+        .flatten()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Extracts id of missing object from error of libgit2, which looks like
+/// "object not found - no match for id (<oid>)"
+fn missing_object(error: &git2::Error) -> Option<Oid> {
+    if error.code() != ErrorCode::NotFound || error.class() != ErrorClass::Odb {
+        return None;
+    }
+    let (_, id) = error.message().rsplit_once('(')?;
+    Oid::from_str(id.trim_end_matches(')')).ok()
+}
+
+/// Fetches objects that are not in repository yet from origin, the same way
+/// as git itself does when it needs them in partial clone
+///
+/// Only objects are added and no reference is updated, so this is safe
+/// under shared lock of cached clone, see [`crate::lock::CacheLock`].
+fn fetch_missing(
+    repo: &Repository,
+    git: &GitSettings,
+    oids: impl IntoIterator<Item = Oid>,
+) -> Result<()> {
+    let odb = repo.odb()?;
+    let missing = oids
+        .into_iter()
+        .filter(|oid| !odb.exists(*oid))
+        .map(|oid| oid.to_string())
+        .collect::<HashSet<_>>();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    log::info!("Fetching {} missing objects from origin", missing.len());
+
+    let input = missing
+        .iter()
+        .map(|oid| format!("{}\n", oid))
+        .collect::<String>();
+    git.run_network_with_input(
+        repo.path(),
+        &[
+            "-c",
+            "fetch.negotiationAlgorithm=noop",
+            "fetch",
+            "origin",
+            "--no-tags",
+            "--no-write-fetch-head",
+            "--recurse-submodules=no",
+            "--filter=blob:none",
+            "--stdin",
+        ],
+        &input,
+    )
+    .wrap_err("Failed to fetch missing objects")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_object() {
+        let oid = "61780798228d17af2d34fce4cfbdf35556832472";
+        let error = git2::Error::new(
+            ErrorCode::NotFound,
+            ErrorClass::Odb,
+            format!("object not found - no match for id ({})", oid),
+        );
+        assert_eq!(missing_object(&error), Some(Oid::from_str(oid).unwrap()));
+
+        let error = git2::Error::new(ErrorCode::NotFound, ErrorClass::Reference, "not found");
+        assert_eq!(missing_object(&error), None);
+    }
+}
@@ -0,0 +1,91 @@
+use std::{fmt::Display, str::FromStr};
+
+use regex::Regex;
+
+const REGEX_PREFIX: &str = "regex:";
+
+/// Pattern to match branch names against
+///
+/// By default pattern is a glob, where `*` matches any sequence of characters
+/// (including `/`) and `?` matches any single character. Patterns prefixed
+/// with `regex:` are regular expressions, which have to match whole name.
+#[derive(Clone, Debug)]
+pub(crate) struct BranchPattern {
+    pattern: String,
+    regex: Regex,
+}
+
+impl BranchPattern {
+    pub(crate) fn matches(&self, name: &str) -> bool {
+        self.regex.is_match(name)
+    }
+}
+
+impl FromStr for BranchPattern {
+    type Err = regex::Error;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let expression = match pattern.strip_prefix(REGEX_PREFIX) {
+            Some(expression) => format!("^(?:{})$", expression),
+            None => glob_to_regex(pattern),
+        };
+        Ok(BranchPattern {
+            pattern: pattern.to_owned(),
+            regex: Regex::new(&expression)?,
+        })
+    }
+}
+
+impl Display for BranchPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut expression = String::from("^");
+    let mut buf = [0; 4];
+    for c in glob.chars() {
+        match c {
+            '*' => expression.push_str(".*"),
+            '?' => expression.push('.'),
+            _ => expression.push_str(&regex::escape(c.encode_utf8(&mut buf))),
+        }
+    }
+    expression.push('$');
+    expression
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_pattern() {
+        let pattern: BranchPattern = "renovate/*".parse().unwrap();
+        assert!(pattern.matches("renovate/serde-1.x"));
+        assert!(pattern.matches("renovate/nested/branch"));
+        assert!(!pattern.matches("feature/renovate/serde"));
+        assert!(!pattern.matches("renovate"));
+
+        let pattern: BranchPattern = "release-?.x".parse().unwrap();
+        assert!(pattern.matches("release-1.x"));
+        assert!(!pattern.matches("release-10.x"));
+        assert!(!pattern.matches("release-1yx"));
+    }
+
+    #[test]
+    fn test_regex_pattern() {
+        let pattern: BranchPattern = "regex:(dependabot|renovate)/.+".parse().unwrap();
+        assert!(pattern.matches("dependabot/cargo/serde"));
+        assert!(pattern.matches("renovate/serde"));
+        assert!(!pattern.matches("feature/dependabot/serde"));
+        assert!("regex:(unclosed".parse::<BranchPattern>().is_err());
+    }
+
+    #[test]
+    fn test_pattern_display() {
+        let pattern: BranchPattern = "feature/*".parse().unwrap();
+        assert_eq!(pattern.to_string(), "feature/*");
+    }
+}
@@ -0,0 +1,78 @@
+use std::str::FromStr;
+
+/// Prefix under which pull/merge requests are named as branches
+pub(crate) const PREFIX: &str = "pr/";
+
+/// Refspecs fetching GitHub pull requests and GitLab merge requests,
+/// so that they appear as `origin/pr/<number>` remote-tracking branches
+pub(crate) const REFSPECS: [&str; 2] = [
+    "+refs/pull/*/head:refs/remotes/origin/pr/*",
+    "+refs/merge-requests/*/head:refs/remotes/origin/pr/*",
+];
+
+/// Base branch of a pull request, defined as `NUMBER=BRANCH`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PullRequestBase {
+    pub number: String,
+    pub branch: String,
+}
+
+impl FromStr for PullRequestBase {
+    type Err = String;
+
+    fn from_str(definition: &str) -> Result<Self, Self::Err> {
+        let (number, branch) = definition
+            .split_once('=')
+            .ok_or_else(|| format!("expected NUMBER=BRANCH, got '{}'", definition))?;
+        let number = number.strip_prefix(PREFIX).unwrap_or(number);
+        if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!(
+                "pull request number is not valid in '{}'",
+                definition
+            ));
+        }
+        if branch.is_empty() {
+            return Err(format!("base branch is empty in '{}'", definition));
+        }
+        Ok(PullRequestBase {
+            number: number.to_owned(),
+            branch: branch.to_owned(),
+        })
+    }
+}
+
+/// Returns number of pull request if branch is one, i.e. it is named
+/// like `pr/42` or `origin/pr/42`
+pub(crate) fn number(branch: &str) -> Option<&str> {
+    let number = branch
+        .strip_prefix("origin/")
+        .unwrap_or(branch)
+        .strip_prefix(PREFIX)?;
+    (!number.is_empty() && number.chars().all(|c| c.is_ascii_digit())).then_some(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pull_request_base_parse() {
+        let expected = PullRequestBase {
+            number: "42".to_string(),
+            branch: "release/1.0".to_string(),
+        };
+        assert_eq!("42=release/1.0".parse(), Ok(expected.clone()));
+        assert_eq!("pr/42=release/1.0".parse(), Ok(expected));
+        assert!("42".parse::<PullRequestBase>().is_err());
+        assert!("feature=main".parse::<PullRequestBase>().is_err());
+        assert!("42=".parse::<PullRequestBase>().is_err());
+    }
+
+    #[test]
+    fn test_number() {
+        assert_eq!(number("pr/42"), Some("42"));
+        assert_eq!(number("origin/pr/42"), Some("42"));
+        assert_eq!(number("pr/feature"), None);
+        assert_eq!(number("feature/42"), None);
+    }
+}
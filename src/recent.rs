@@ -1,15 +1,184 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
 use eyre::{Context, Result};
-use git2::{BranchType, Repository};
+use git2::{BranchType, Commit, Oid, Reference, ReferenceType, Repository};
+
+use crate::{
+    cli::{BranchKind, SortOrder},
+    pattern::BranchPattern,
+    pull_request::{self, PullRequestBase},
+};
+
+/// Settings of how branches are selected for analysis
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Selection {
+    /// Number of most recent branches to take when none are listed explicitly
+    pub recent: usize,
+    /// If not empty, only branches matching any of these patterns are selected
+    pub include: Vec<BranchPattern>,
+    /// Branches matching any of these patterns are never selected
+    pub exclude: Vec<BranchPattern>,
+    /// Order in which most recent branches are chosen
+    pub sort: SortOrder,
+    /// Only branches with last commit not older than this are chosen
+    pub since: Option<Duration>,
+    /// Only branches with last commit at least this old are chosen
+    pub until: Option<Duration>,
+    /// Only branches, where last commit or any commit not in base branch
+    /// was authored by someone with name or email containing this
+    pub author: Option<String>,
+    /// Branch to compare others with, default branch of origin if not set
+    pub base: Option<String>,
+    /// Whether default branch of origin is always chosen first among recent ones
+    pub default_branch: bool,
+    /// Base branches of pull requests, default branch of origin is used for others
+    pub pull_request_bases: Vec<PullRequestBase>,
+}
+
+impl Selection {
+    fn matches(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches(name)))
+            && !self.exclude.iter().any(|p| p.matches(name))
+    }
+}
+
+/// Where branches for analysis are taken from and how they are named
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BranchSource {
+    /// Branches of "origin" remote, named without "origin/" prefix
+    Origin,
+    /// Branches of a local repository, named as git shows them,
+    /// i.e. "main" for local branch and "origin/main" for remote-tracking one
+    Local(BranchKind),
+}
 
-pub(crate) fn get_recent_branches(repo: &Repository, limit: usize) -> Result<Vec<String>> {
+impl BranchSource {
+    fn branch_type(&self) -> Option<BranchType> {
+        match self {
+            BranchSource::Origin => Some(BranchType::Remote),
+            BranchSource::Local(BranchKind::Local) => Some(BranchType::Local),
+            BranchSource::Local(BranchKind::Remote) => Some(BranchType::Remote),
+            BranchSource::Local(BranchKind::All) => None,
+        }
+    }
+
+    /// Finds reference of the branch with given name
+    pub(crate) fn find_reference<'repo>(
+        &self,
+        repo: &'repo Repository,
+        name: &str,
+    ) -> Result<Reference<'repo>> {
+        let candidates = match self {
+            BranchSource::Origin => vec![format!("refs/remotes/origin/{}", name)],
+            BranchSource::Local(BranchKind::Local) => vec![format!("refs/heads/{}", name)],
+            BranchSource::Local(BranchKind::Remote) => vec![format!("refs/remotes/{}", name)],
+            BranchSource::Local(BranchKind::All) => vec![
+                format!("refs/heads/{}", name),
+                format!("refs/remotes/{}", name),
+            ],
+        };
+        candidates
+            .iter()
+            .find_map(|candidate| repo.find_reference(candidate).ok())
+            .ok_or_else(|| eyre::eyre!("branch {} not found", name))
+    }
+}
+
+/// Resolves default branch of origin, which `refs/remotes/origin/HEAD` points to
+///
+/// Returned name follows naming of branches in the source, if such branch exists there.
+pub(crate) fn default_branch(repo: &Repository, source: BranchSource) -> Option<String> {
+    let head = repo.find_reference("refs/remotes/origin/HEAD").ok()?;
+    let name = head
+        .symbolic_target()?
+        .strip_prefix("refs/remotes/origin/")?
+        .to_owned();
+    let remote_name = format!("origin/{}", name);
+    let has_local = || repo.find_branch(&name, BranchType::Local).is_ok();
+    match source {
+        BranchSource::Origin => Some(name),
+        BranchSource::Local(BranchKind::Remote) => Some(remote_name),
+        BranchSource::Local(BranchKind::Local) => has_local().then_some(name),
+        BranchSource::Local(BranchKind::All) if has_local() => Some(name),
+        BranchSource::Local(BranchKind::All) => Some(remote_name),
+    }
+}
+
+/// Returns branches listed explicitly, or most recent ones if none are listed
+pub(crate) fn select_branches(
+    repo: &Repository,
+    branches: Vec<String>,
+    source: BranchSource,
+    selection: &Selection,
+) -> Result<Vec<String>> {
+    let branches = if branches.is_empty() {
+        get_recent_branches(repo, source, selection)?
+    } else {
+        branches
+            .into_iter()
+            .filter(|name| {
+                let selected = selection.matches(name);
+                if !selected {
+                    log::warn!(
+                        "Branch {} is skipped, as it is filtered out by --include or --exclude",
+                        name
+                    );
+                }
+                selected
+            })
+            .collect()
+    };
+
+    Ok(add_pull_request_bases(repo, branches, source, selection))
+}
+
+/// Adds base branches of selected pull requests, so that each pull request
+/// is analysed against the branch it is going to be merged into
+fn add_pull_request_bases(
+    repo: &Repository,
+    mut branches: Vec<String>,
+    source: BranchSource,
+    selection: &Selection,
+) -> Vec<String> {
+    let bases = branches
+        .iter()
+        .filter_map(|name| pull_request::number(name))
+        .filter_map(|number| {
+            selection
+                .pull_request_bases
+                .iter()
+                .find(|base| base.number == number)
+                .map(|base| base.branch.clone())
+                .or_else(|| default_branch(repo, source))
+        })
+        .collect::<Vec<_>>();
+    for base in bases {
+        if !branches.contains(&base) {
+            branches.push(base);
+        }
+    }
+    branches
+}
+
+pub(crate) fn get_recent_branches(
+    repo: &Repository,
+    source: BranchSource,
+    selection: &Selection,
+) -> Result<Vec<String>> {
     // firstly we collect all branches
-    let mut branches = repo
-        .branches(Some(BranchType::Remote))
+    let branches = repo
+        .branches(source.branch_type())
         .context("failed to retrieve git branches")?
         // exiting if could not get any of branches
         .collect::<Result<Vec<_>, _>>()
         .context("failed to retrieve some of git branches")?
         .into_iter()
+        // symbolic references, such as "origin/HEAD", only point to other branches
+        .filter(|(branch, _)| branch.get().kind() != Some(ReferenceType::Symbolic))
         // extract their names
         .flat_map(|(branch, _)| match branch.name() {
             Ok(Some(name)) => {
@@ -28,7 +197,17 @@ pub(crate) fn get_recent_branches(repo: &Repository, limit: usize) -> Result<Vec
                 None
             }
         })
-        .filter(|(_, name)| name != &"origin/HEAD")
+        .filter(|(_, name)| match source {
+            BranchSource::Origin => name.starts_with("origin/") && name != "origin/HEAD",
+            BranchSource::Local(_) => true,
+        })
+        // names are matched as they are shown to user
+        .map(|(branch, name)| match source {
+            // branch names without "origin/" prefix
+            BranchSource::Origin => (branch, name.replacen("origin/", "", 1)),
+            BranchSource::Local(_) => (branch, name),
+        })
+        .filter(|(_, name)| selection.matches(name))
         // then we get the last commit of each branch
         .map(|(branch, name)| match branch.get().peel_to_commit() {
             Ok(commit) => Ok((commit, name)),
@@ -38,19 +217,148 @@ pub(crate) fn get_recent_branches(repo: &Repository, limit: usize) -> Result<Vec
         .collect::<std::result::Result<Vec<_>, _>>()
         .context("failed to read last commits of git branches")?;
 
-    // then we sort them by last commit date, exiting on error
-    branches.sort_unstable_by(|(commit_a, _), (commit_b, _)| {
-        // inverse cmp to get descending order
-        commit_b
-            .committer()
-            .when()
-            .cmp(&commit_a.committer().when())
-    });
-
-    // returns branch names without "origin/" prefix
-    Ok(branches
-        .iter()
-        .map(|(_, name)| name.replacen("origin/", "", 1))
-        .take(limit)
+    // base is only needed for some of sort orders and filters
+    let needs_base = selection.author.is_some()
+        || matches!(
+            selection.sort,
+            SortOrder::AheadOfBase | SortOrder::BehindBase
+        );
+    let base = match selection
+        .base
+        .clone()
+        .or_else(|| default_branch(repo, source))
+    {
+        Some(base) if needs_base => {
+            Some(source.find_reference(repo, &base)?.peel_to_commit()?.id())
+        }
+        _ => None,
+    };
+
+    // default branch is always chosen, regardless of its age and authors
+    let default = match selection.default_branch {
+        true => default_branch(repo, source),
+        false => None,
+    };
+    let mut default_found = false;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let mut selected = Vec::new();
+    for (commit, name) in branches {
+        if default.as_ref() == Some(&name) {
+            default_found = true;
+            continue;
+        }
+
+        let age = now - commit.committer().when().seconds();
+        if selection
+            .since
+            .is_some_and(|since| age > since.as_secs() as i64)
+            || selection
+                .until
+                .is_some_and(|until| age < until.as_secs() as i64)
+        {
+            continue;
+        }
+        if let Some(author) = &selection.author {
+            if !authored_by(repo, &commit, base, author)? {
+                continue;
+            }
+        }
+        selected.push((commit, name));
+    }
+
+    sort_branches(repo, &mut selected, selection.sort, base)?;
+
+    Ok(default
+        .filter(|_| default_found)
+        .into_iter()
+        .chain(selected.into_iter().map(|(_, name)| name))
+        .take(selection.recent)
         .collect())
 }
+
+fn sort_branches(
+    repo: &Repository,
+    branches: &mut [(Commit, String)],
+    sort: SortOrder,
+    base: Option<Oid>,
+) -> Result<()> {
+    match sort {
+        SortOrder::CommitterDate => {
+            // inverse cmp to get descending order
+            branches.sort_by(|(commit_a, _), (commit_b, _)| {
+                commit_b
+                    .committer()
+                    .when()
+                    .cmp(&commit_a.committer().when())
+            })
+        }
+        SortOrder::AuthorDate => branches.sort_by(|(commit_a, _), (commit_b, _)| {
+            commit_b.author().when().cmp(&commit_a.author().when())
+        }),
+        SortOrder::Name => branches.sort_by(|(_, name_a), (_, name_b)| name_a.cmp(name_b)),
+        SortOrder::AheadOfBase | SortOrder::BehindBase => {
+            let base = base.ok_or_else(|| {
+                eyre::eyre!("default branch could not be determined, use --base to set it")
+            })?;
+            let mut counts = HashMap::new();
+            for (commit, _) in branches.iter() {
+                let (ahead, behind) = repo
+                    .graph_ahead_behind(commit.id(), base)
+                    .context("failed to compare branches with base")?;
+                let count = match sort {
+                    SortOrder::AheadOfBase => ahead,
+                    _ => behind,
+                };
+                counts.insert(commit.id(), count);
+            }
+            // most diverged branches go first, then most recently updated
+            branches.sort_by_key(|(commit, _)| {
+                (
+                    Reverse(counts[&commit.id()]),
+                    Reverse(commit.committer().when()),
+                )
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Checks whether last commit of the branch, or any of commits that are
+/// not in base, was authored by someone with name or email containing `author`
+fn authored_by(
+    repo: &Repository,
+    commit: &Commit,
+    base: Option<Oid>,
+    author: &str,
+) -> Result<bool> {
+    let author = author.to_lowercase();
+    let matches = |commit: &Commit| {
+        let signature = commit.author();
+        format!(
+            "{} <{}>",
+            String::from_utf8_lossy(signature.name_bytes()),
+            String::from_utf8_lossy(signature.email_bytes())
+        )
+        .to_lowercase()
+        .contains(&author)
+    };
+
+    if matches(commit) {
+        return Ok(true);
+    }
+
+    // without base there is no way to tell which commits are unique to the branch
+    let Some(base) = base else {
+        return Ok(false);
+    };
+    let mut walk = repo.revwalk()?;
+    walk.push(commit.id())?;
+    walk.hide(base)?;
+    for oid in walk {
+        if matches(&repo.find_commit(oid?)?) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
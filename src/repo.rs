@@ -1,31 +1,126 @@
 use eyre::{Context, Result};
 use git2::Repository;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
-use std::{env, fs};
+use indicatif::ProgressBar;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
+use crate::cache;
+use crate::cli::{CloneStrategy, Transport};
 use crate::clone::clone_repo;
+use crate::git::{redact, GitSettings};
 use crate::interactive::Repo;
+use crate::lock::{CacheLock, LockMode};
+use crate::native;
+use crate::pull_request;
 use crate::Probranchinator;
 
+/// Options of how remote repository is retrieved into cache
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RepoOptions {
+    /// Whether GitHub pull requests and GitLab merge requests are fetched too
+    pub pull_requests: bool,
+    /// Directory to cache clones in instead of the default one
+    pub cache_dir: Option<PathBuf>,
+    /// How repository is cloned into cache
+    pub clone: CloneStrategy,
+    /// Whether git command line tool or libgit2 is used to clone and fetch
+    pub transport: Transport,
+    /// Whether cached clone is used without fetching it
+    pub offline: bool,
+    /// Cached clone is not fetched if it was fetched more recently than this
+    pub max_age: Option<Duration>,
+    /// How long to wait for other processes using the same cached clone
+    pub lock_timeout: Duration,
+    /// How git command line tool is run
+    pub git: GitSettings,
+    /// Spinner to report progress of cloning and fetching to
+    pub progress: Option<ProgressBar>,
+}
+
 impl Repo for Probranchinator {
-    fn get_repo(&self, remote_url: &str) -> Result<(Repository, PathBuf, bool)> {
-        // Create the directory for the repositories under the system temporary directory
-        let mut tmp_path = env::temp_dir();
-        tmp_path.push("probranchinator");
-        fs::create_dir_all(&tmp_path)
-            .with_context(|| format!("Failed to create directory {:?}", tmp_path))?;
+    fn get_repo(
+        &self,
+        remote_url: &str,
+        options: &RepoOptions,
+    ) -> Result<(Repository, PathBuf, bool, Option<CacheLock>)> {
+        // unsupported clone is rejected before cached clone is touched,
+        // which would otherwise be removed to be cloned again
+        if options.transport == Transport::Libgit2 && !options.offline {
+            native::check_strategy(options.clone)?;
+        }
+
+        // Create the directory for the repositories
+        let root = cache::cache_root(options.cache_dir.as_deref());
+        fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create directory {:?}", root))?;
+
+        let tmp_path = cache::cache_path(&root, remote_url);
+
+        // offline clone is only read, otherwise it is changed by clone or fetch
+        // and then kept locked for reading until analysis is done
+        let lock_mode = if options.offline {
+            LockMode::Shared
+        } else {
+            LockMode::Exclusive
+        };
+        let lock = CacheLock::acquire(&tmp_path, lock_mode, options.lock_timeout)?;
+
+        let mut have_cached_repo = tmp_path.is_dir();
 
-        // Generate subfolder name from hash of git remote url
-        let mut hasher = DefaultHasher::new();
-        remote_url.hash(&mut hasher);
-        let subfolder_name = format!("{:x}", hasher.finish());
+        if options.offline {
+            if !have_cached_repo {
+                eyre::bail!(
+                    "Remote {} is not cached in {:?} yet, run without --offline to clone it",
+                    redact(remote_url),
+                    root
+                );
+            }
+            let pull_requests_fetched =
+                cache::read_metadata(&tmp_path).is_some_and(|metadata| metadata.pull_requests);
+            if options.pull_requests && !pull_requests_fetched {
+                log::warn!(
+                    "Pull requests of {} were not fetched into cache, they cannot be fetched offline",
+                    redact(remote_url)
+                );
+            }
+            let repo = Repository::open(&tmp_path).with_context(|| {
+                format!(
+                    "Failed to open repository in directory {:?}",
+                    tmp_path.display()
+                )
+            })?;
+            return Ok((repo, tmp_path, have_cached_repo, Some(lock)));
+        }
 
-        // Create the full path to the new folder
-        tmp_path.push(subfolder_name);
+        if have_cached_repo {
+            if let Err(problem) = repair_clone(&tmp_path, remote_url) {
+                log::warn!(
+                    "Cached clone of {} in {:?} is broken, cloning it again: {}",
+                    redact(remote_url),
+                    tmp_path,
+                    problem
+                );
+                cache::remove_clone(&tmp_path)?;
+                have_cached_repo = false;
+            }
+        }
 
-        let have_cached_repo = tmp_path.is_dir();
+        // clones created before strategies could be chosen are full ones
+        let cached_strategy = cache::read_metadata(&tmp_path)
+            .map_or(CloneStrategy::Full, |metadata| {
+                metadata.clone.unwrap_or(CloneStrategy::Full)
+            });
+        if have_cached_repo && cached_strategy != options.clone {
+            log::warn!(
+                "Cached clone of {} was created with --clone={}, cloning it again with --clone={}",
+                redact(remote_url),
+                cached_strategy,
+                options.clone
+            );
+            cache::remove_clone(&tmp_path)?;
+            have_cached_repo = false;
+        }
 
         if !have_cached_repo {
             // currently all kinds of clones via git2-rs or gitoxide
@@ -34,45 +129,237 @@ impl Repo for Probranchinator {
             // git2 cannot be built for macos because it needs openssl and cross does not provide
             // also git2 cannot deal with SSH at the moment on windows
             // gitoxide fails on some random repositories with "not supported" errors
-            // hence we use the git command line tool for now only for cloning
-            clone_repo(remote_url, &tmp_path)?;
-        } else {
-            // fetch using git command line tool
-            // again this is due to none of libraries being able to properly fetch on all platforms
-            let mut cmd = std::process::Command::new("git");
-            cmd.arg("fetch");
-            cmd.arg("origin");
-            cmd.current_dir(&tmp_path);
-            let child = cmd.spawn()?;
-            let output = child.wait_with_output()?;
-            if !output.status.success() {
-                eyre::bail!("Failed to fetch repository");
+            // hence we use the git command line tool by default,
+            // unless libgit2 is explicitly chosen
+            let staging_path = cache::staging_path(&tmp_path);
+            if staging_path.exists() {
+                log::warn!("Removing leftovers of interrupted clone {:?}", staging_path);
+                fs::remove_dir_all(&staging_path).with_context(|| {
+                    format!("Failed to remove interrupted clone {:?}", staging_path)
+                })?;
+            }
+            match options.transport {
+                Transport::Cli => {
+                    clone_repo(remote_url, &staging_path, options.clone, &options.git)?
+                }
+                Transport::Libgit2 => {
+                    options.git.with_retries(|| {
+                        // failed attempt might leave partial clone behind
+                        if staging_path.exists() {
+                            fs::remove_dir_all(&staging_path)?;
+                        }
+                        native::clone_repo(
+                            remote_url,
+                            &staging_path,
+                            options.clone,
+                            options.progress.as_ref(),
+                        )
+                    })?
+                }
             }
+            fs::rename(&staging_path, &tmp_path).with_context(|| {
+                format!(
+                    "Failed to move clone from {:?} to {:?}",
+                    staging_path, tmp_path
+                )
+            })?;
+        }
+
+        // freshly cloned repository only needs to be fetched again
+        // if pull requests have to be fetched too
+        let refspecs_changed = configure_pull_requests(&tmp_path, options.pull_requests)?;
 
-            // then prune all branches that are not on origin anymore
-            // also using git command line tool, because git2-rs
-            // fails with "this remote has never connected", probably
-            // due to the fact that we cloned with git command line tool
-
-            let mut cmd = std::process::Command::new("git");
-            cmd.arg("remote");
-            cmd.arg("prune");
-            cmd.arg("origin");
-            cmd.current_dir(&tmp_path);
-            let child = cmd.spawn()?;
-            let output = child.wait_with_output()?;
-            if !output.status.success() {
-                eyre::bail!("Failed to prune repository");
+        let fetched = if have_cached_repo && !refspecs_changed && is_fresh(&tmp_path, options) {
+            log::info!(
+                "Cached clone of {} was fetched recently, not fetching it",
+                redact(remote_url)
+            );
+            false
+        } else {
+            if have_cached_repo || refspecs_changed {
+                match options.transport {
+                    Transport::Cli => fetch_repo(&tmp_path, &options.git)?,
+                    Transport::Libgit2 => options
+                        .git
+                        .with_retries(|| native::fetch(&tmp_path, options.progress.as_ref()))?,
+                }
             }
+            true
+        };
+
+        if fetched {
+            cache::write_metadata(&tmp_path, remote_url, options)?;
         }
 
+        // clone is only opened under shared lock, as another process
+        // might change it while lock is downgraded
+        let lock = lock.downgrade(options.lock_timeout)?;
         let repo = Repository::open(&tmp_path).with_context(|| {
             format!(
                 "Failed to open repository in directory {:?}",
                 tmp_path.display()
             )
         })?;
+        Ok((repo, tmp_path, have_cached_repo, Some(lock)))
+    }
+
+    fn open_repo(&self, path: &Path) -> Result<Repository> {
+        // discover allows to point to any directory inside of the repository
+        let repo = Repository::discover(path)
+            .with_context(|| format!("Failed to open repository at {:?}", path.display()))?;
+
+        // user's repository is only read, so any objects written during analysis
+        // go to in-memory backend, which has highest priority, instead of disk
+        repo.odb()?
+            .add_new_mempack_backend(1000)
+            .context("Failed to prepare in-memory object storage")?;
+
+        Ok(repo)
+    }
+
+    fn discover_remote(&self, path: &Path, remote_name: &str) -> Result<String> {
+        let repo = Repository::discover(path).with_context(|| {
+            format!(
+                "Remote was not provided and {:?} is not inside of a git repository",
+                path.display()
+            )
+        })?;
+        let remote = repo.find_remote(remote_name).with_context(|| {
+            format!(
+                "Remote was not provided and repository at {:?} has no remote named '{}'",
+                repo.workdir().unwrap_or_else(|| repo.path()).display(),
+                remote_name
+            )
+        })?;
+        match remote.url() {
+            Some(url) => Ok(url.to_owned()),
+            None => eyre::bail!("URL of remote '{}' is not valid UTF-8", remote_name),
+        }
+    }
+}
+
+/// Checks that cached clone can be used and cleans up after processes that
+/// were killed while using it, returns problem if it has to be cloned again
+///
+/// Has to be called while holding exclusive lock of the clone, as otherwise
+/// lock files of git could belong to another running process.
+fn repair_clone(path: &Path, remote_url: &str) -> Result<(), String> {
+    let repo = Repository::open(path).map_err(|e| format!("cannot be opened: {}", e))?;
+
+    let remote = repo
+        .find_remote("origin")
+        .map_err(|e| format!("has no origin: {}", e))?;
+    match remote.url() {
+        Some(url) if cache::same_remote(url, remote_url) => {}
+        url => return Err(format!("origin points to {:?} instead", url.map(redact))),
+    }
+
+    for lock in stale_locks(repo.path()) {
+        fs::remove_file(&lock)
+            .map_err(|e| format!("stale lock file {:?} cannot be removed: {}", lock, e))?;
+        log::warn!("Removed stale lock file {:?}", lock);
+    }
+
+    if repo.state() != git2::RepositoryState::Clean {
+        log::warn!(
+            "Cleaned up {:?} state left in {:?}",
+            repo.state(),
+            repo.path()
+        );
+        repo.cleanup_state()
+            .map_err(|e| format!("state cannot be cleaned up: {}", e))?;
+    }
+
+    Ok(())
+}
 
-        Ok((repo, tmp_path, have_cached_repo))
+/// Finds lock files git leaves behind when it is killed, e.g. `index.lock`
+fn stale_locks(git_dir: &Path) -> Vec<PathBuf> {
+    let mut locks = Vec::new();
+    let mut dirs = vec![git_dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                // only references are nested, other directories have no locks of interest
+                if path.starts_with(git_dir.join("refs")) {
+                    dirs.push(path);
+                }
+            } else if path
+                .extension()
+                .is_some_and(|extension| extension == "lock")
+            {
+                locks.push(path);
+            }
+        }
+    }
+    locks
+}
+
+/// Checks whether cached clone was fetched within maximum age from options
+fn is_fresh(path: &Path, options: &RepoOptions) -> bool {
+    match (options.max_age, cache::fetched_at(path)) {
+        (Some(max_age), Ok(fetched_at)) => SystemTime::now()
+            .duration_since(fetched_at)
+            .map_or(true, |age| age <= max_age),
+        _ => false,
+    }
+}
+
+fn fetch_repo(path: &Path, git: &GitSettings) -> Result<()> {
+    // fetch using git command line tool
+    // again this is due to none of libraries being able to properly fetch on all platforms
+    git.run_network(path, &["fetch", "--quiet", "origin"])
+        .context("Failed to fetch repository")?;
+
+    // then prune all branches that are not on origin anymore
+    // also using git command line tool, because git2-rs
+    // fails with "this remote has never connected", probably
+    // due to the fact that we cloned with git command line tool
+    git.run_network(path, &["remote", "prune", "origin"])
+        .context("Failed to prune repository")?;
+    Ok(())
+}
+
+/// Adds or removes refspecs of pull requests in fetch configuration of cached
+/// repository, returns whether configuration was changed
+fn configure_pull_requests(path: &Path, enabled: bool) -> Result<bool> {
+    let repo = Repository::open(path)
+        .with_context(|| format!("Failed to open repository in directory {:?}", path))?;
+    let configured = repo
+        .find_remote("origin")?
+        .fetch_refspecs()?
+        .iter()
+        .flatten()
+        .filter(|refspec| pull_request::REFSPECS.contains(refspec))
+        .count();
+
+    if enabled && configured < pull_request::REFSPECS.len() {
+        // remove first to not end up with duplicates of partially configured refspecs
+        remove_pull_request_refspecs(&repo)?;
+        for refspec in pull_request::REFSPECS {
+            repo.remote_add_fetch("origin", refspec)
+                .context("Failed to configure fetching of pull requests")?;
+        }
+        return Ok(true);
+    }
+    if !enabled && configured > 0 {
+        // pull requests that were fetched before are pruned on next fetch
+        remove_pull_request_refspecs(&repo)?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+fn remove_pull_request_refspecs(repo: &Repository) -> Result<()> {
+    let mut config = repo.config()?;
+    match config.remove_multivar(
+        "remote.origin.fetch",
+        r"^\+refs/(pull|merge-requests)/\*/head:refs/remotes/origin/pr/\*$",
+    ) {
+        Err(e) if e.code() != git2::ErrorCode::NotFound => {
+            Err(e).context("Failed to remove fetching of pull requests from configuration")
+        }
+        _ => Ok(()),
     }
 }
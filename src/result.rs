@@ -1,50 +1,139 @@
 use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
-use tabled::Tabled;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
 pub(crate) enum MergeAnalysisStatus {
     UpToDate,
     FastForward,
     None,
-    Error { message: String },
+    Error {
+        message: String,
+    },
     Normal,
+    #[default]
     Unknown,
     Conflicts,
+    /// Merge base was not found within history of shallow repository
+    BeyondShallowDepth,
+}
+
+impl MergeAnalysisStatus {
+    /// Stable machine-readable name of the status
+    pub fn code(&self) -> &'static str {
+        match self {
+            MergeAnalysisStatus::UpToDate => "up_to_date",
+            MergeAnalysisStatus::FastForward => "fast_forward",
+            MergeAnalysisStatus::None => "none",
+            MergeAnalysisStatus::Error { .. } => "error",
+            MergeAnalysisStatus::Normal => "normal",
+            MergeAnalysisStatus::Unknown => "unknown",
+            MergeAnalysisStatus::Conflicts => "conflicts",
+            MergeAnalysisStatus::BeyondShallowDepth => "beyond_shallow_depth",
+        }
+    }
+
+    /// Short form of the status, used where full text does not fit
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            MergeAnalysisStatus::UpToDate => "✅✅",
+            MergeAnalysisStatus::FastForward => "🚀✅",
+            MergeAnalysisStatus::None | MergeAnalysisStatus::Error { .. } => "❌❌",
+            MergeAnalysisStatus::Unknown => "❌🤔",
+            MergeAnalysisStatus::Conflicts => "🚧🔧",
+            MergeAnalysisStatus::Normal => "🤝✅",
+            MergeAnalysisStatus::BeyondShallowDepth => "🌊🤔",
+        }
+    }
 }
 
 impl Display for MergeAnalysisStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} ", self.emoji())?;
         match self {
             MergeAnalysisStatus::UpToDate => {
-                write!(f, "✅✅ No changes: already up-to-date.")
+                write!(f, "No changes: already up-to-date.")
             }
             MergeAnalysisStatus::FastForward => {
-                write!(f, "🚀✅ No confilcts: fast-forward merge is possible.")
+                write!(f, "No confilcts: fast-forward merge is possible.")
             }
             MergeAnalysisStatus::None => {
-                write!(f, "❌❌ No merge is possible - analysis gave none.")
+                write!(f, "No merge is possible - analysis gave none.")
             }
             MergeAnalysisStatus::Error { message } => {
-                write!(f, "❌❌ No merge is possible - {}.", message)
+                write!(f, "No merge is possible - {}.", message)
             }
-            MergeAnalysisStatus::Unknown => write!(f, "❌🤔 Unknown merge analysis result."),
+            MergeAnalysisStatus::Unknown => write!(f, "Unknown merge analysis result."),
             MergeAnalysisStatus::Conflicts => {
-                write!(f, "🚧🔧 Found conflicts, have to resolve them manually.")
+                write!(f, "Found conflicts, have to resolve them manually.")
             }
             MergeAnalysisStatus::Normal => {
-                write!(f, "🤝✅ No conflicts: automatic merge is possible.")
+                write!(f, "No conflicts: automatic merge is possible.")
+            }
+            MergeAnalysisStatus::BeyondShallowDepth => {
+                write!(
+                    f,
+                    "Merge base is beyond shallow depth, deepen history to analyse."
+                )
             }
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Tabled, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub(crate) struct MergeAnalysisResult {
     pub from_branch: String,
     pub to_branch: String,
     pub status: MergeAnalysisStatus,
+    /// Whether branch being merged is the default branch of repository
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub from_default: bool,
+    /// Whether branch being merged into is the default branch of repository
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub to_default: bool,
+    /// Commit at the tip of branch being merged, unless it is working tree
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_commit: Option<String>,
+    /// Commit at the tip of branch being merged into
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_commit: Option<String>,
+    /// Time of commit at the tip of branch being merged, in RFC 3339 format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_committed_at: Option<String>,
+    /// Time of commit at the tip of branch being merged into, in RFC 3339 format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_committed_at: Option<String>,
+    /// How many commits of branch being merged are not in branch being
+    /// merged into, if it could be counted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ahead: Option<usize>,
+    /// How many commits of branch being merged into are not in branch
+    /// being merged, if it could be counted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub behind: Option<usize>,
+    /// Paths of files that have conflicts, if merge has them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<String>,
+}
+
+impl MergeAnalysisResult {
+    /// Name of branch being merged, marked if it is the default one
+    pub fn display_from_branch(&self) -> String {
+        branch_display(&self.from_branch, self.from_default)
+    }
+
+    /// Name of branch being merged into, marked if it is the default one
+    pub fn display_to_branch(&self) -> String {
+        branch_display(&self.to_branch, self.to_default)
+    }
+}
+
+fn branch_display(name: &str, default: bool) -> String {
+    if default {
+        format!("{} (default)", name)
+    } else {
+        name.to_owned()
+    }
 }
 
 impl Display for MergeAnalysisResult {
@@ -52,7 +141,9 @@ impl Display for MergeAnalysisResult {
         write!(
             f,
             "{} -> {} : {}",
-            self.from_branch, self.to_branch, self.status
+            self.display_from_branch(),
+            self.display_to_branch(),
+            self.status
         )
     }
 }
@@ -96,6 +187,33 @@ mod tests {
             format!("{}", MergeAnalysisStatus::Normal),
             "🤝✅ No conflicts: automatic merge is possible."
         );
+        assert_eq!(
+            format!("{}", MergeAnalysisStatus::BeyondShallowDepth),
+            "🌊🤔 Merge base is beyond shallow depth, deepen history to analyse."
+        );
+    }
+
+    #[test]
+    fn test_merge_analysis_status_code() {
+        use super::MergeAnalysisStatus;
+        assert_eq!(MergeAnalysisStatus::UpToDate.code(), "up_to_date");
+        assert_eq!(
+            MergeAnalysisStatus::BeyondShallowDepth.code(),
+            "beyond_shallow_depth"
+        );
+    }
+
+    #[test]
+    fn test_merge_analysis_status_emoji() {
+        use super::MergeAnalysisStatus;
+        assert_eq!(MergeAnalysisStatus::Normal.emoji(), "🤝✅");
+        assert_eq!(
+            MergeAnalysisStatus::Error {
+                message: "error".to_owned()
+            }
+            .emoji(),
+            MergeAnalysisStatus::None.emoji()
+        );
     }
 
     #[test]
@@ -107,10 +225,24 @@ mod tests {
                 MergeAnalysisResult {
                     from_branch: "from".to_owned(),
                     to_branch: "to".to_owned(),
-                    status: MergeAnalysisStatus::UpToDate
+                    status: MergeAnalysisStatus::UpToDate,
+                    ..Default::default()
                 }
             ),
             "from -> to : ✅✅ No changes: already up-to-date."
         );
+        assert_eq!(
+            format!(
+                "{}",
+                MergeAnalysisResult {
+                    from_branch: "from".to_owned(),
+                    to_branch: "main".to_owned(),
+                    status: MergeAnalysisStatus::Normal,
+                    to_default: true,
+                    ..Default::default()
+                }
+            ),
+            "from -> main (default) : 🤝✅ No conflicts: automatic merge is possible."
+        );
     }
 }
@@ -0,0 +1,93 @@
+use eyre::{Context, Result};
+use git2::{ErrorClass, Oid, Repository};
+
+use crate::git::GitSettings;
+
+/// How many commits history is deepened by at first, doubled on each next try
+const INITIAL_DEEPEN: u32 = 50;
+
+/// Deepens history of shallow repository while merge bases are beyond it
+///
+/// Only cached clones are deepened, user's own repositories are analysed
+/// with zero limit, so that they are never written to.
+///
+/// Deepening is shared by all pairs of branches, so that history is fetched
+/// at most up to the limit in total, however many pairs need it.
+pub(crate) struct Deepening {
+    /// How many more commits history can still be deepened by
+    remaining: u32,
+    next_step: u32,
+    git: GitSettings,
+    /// Commits analysed branches were resolved to, history of which is deepened
+    tips: Vec<Oid>,
+}
+
+impl Deepening {
+    pub(crate) fn new(limit: u32, git: GitSettings, tips: Vec<Oid>) -> Self {
+        Deepening {
+            remaining: limit,
+            next_step: INITIAL_DEEPEN,
+            git,
+            tips,
+        }
+    }
+
+    /// Runs operation that walks history, deepening it while the operation
+    /// reaches shallow boundary, returns `None` if the limit was hit first
+    pub(crate) fn run<T>(
+        &mut self,
+        repo: &Repository,
+        mut operation: impl FnMut() -> Result<T, git2::Error>,
+    ) -> Result<Option<T>> {
+        loop {
+            match operation() {
+                Err(e) if is_shallow_boundary(repo, &e) => {
+                    if self.remaining == 0 {
+                        return Ok(None);
+                    }
+                    let step = self.next_step.min(self.remaining);
+                    deepen(repo, &self.git, step, &self.tips)?;
+                    self.remaining -= step;
+                    self.next_step = self.next_step.saturating_mul(2);
+                }
+                result => return Ok(Some(result?)),
+            }
+        }
+    }
+}
+
+/// libgit2 does not know about shallow history, so walking past its boundary
+/// fails on parents of the oldest commits, which are not in repository
+fn is_shallow_boundary(repo: &Repository, error: &git2::Error) -> bool {
+    repo.is_shallow()
+        && error.class() == ErrorClass::Odb
+        && error.message().starts_with("object not found")
+}
+
+/// Fetches more history of given commits from origin with git command line
+/// tool, as libgit2 cannot fetch into shallow repository
+///
+/// Commits are fetched by their ids without updating any reference, so that
+/// branches stay where they were when analysis started. Only objects and the
+/// list of shallow commits change, which is safe under shared lock of cached
+/// clone, see [`crate::lock::CacheLock`].
+fn deepen(repo: &Repository, git: &GitSettings, commits: u32, tips: &[Oid]) -> Result<()> {
+    log::info!("Deepening shallow history by {} commits", commits);
+
+    let deepen = format!("--deepen={}", commits);
+    let tips = tips.iter().map(Oid::to_string).collect::<Vec<_>>();
+    let mut args = vec![
+        "fetch",
+        "--quiet",
+        "--no-tags",
+        "--no-write-fetch-head",
+        // no configured refspec updates remote-tracking branches either
+        "--refmap=",
+        &deepen,
+        "origin",
+    ];
+    args.extend(tips.iter().map(String::as_str));
+    git.run_network(repo.path(), &args)
+        .wrap_err("Failed to deepen history")?;
+    Ok(())
+}
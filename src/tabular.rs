@@ -0,0 +1,199 @@
+use tabled::builder::Builder;
+
+use crate::{cli::Column, result::MergeAnalysisResult};
+
+/// Columns of `table` and `markdown` outputs, unless chosen with `--columns`
+const TABLE_COLUMNS: [Column; 3] = [Column::FromBranch, Column::ToBranch, Column::Status];
+
+/// Columns of `csv` and `tsv` outputs, unless chosen with `--columns`
+const DELIMITED_COLUMNS: [Column; 4] = [
+    Column::FromBranch,
+    Column::ToBranch,
+    Column::StatusCode,
+    Column::Status,
+];
+
+/// How tabular output is rendered
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TabularStyle {
+    Table,
+    Markdown,
+    Csv,
+    Tsv,
+}
+
+impl TabularStyle {
+    fn default_columns(&self) -> &'static [Column] {
+        match self {
+            TabularStyle::Table | TabularStyle::Markdown => &TABLE_COLUMNS,
+            TabularStyle::Csv | TabularStyle::Tsv => &DELIMITED_COLUMNS,
+        }
+    }
+
+    /// Whether output is meant for machines, which get branch names as they
+    /// are instead of marked default branch
+    fn is_delimited(&self) -> bool {
+        matches!(self, TabularStyle::Csv | TabularStyle::Tsv)
+    }
+}
+
+/// Renders results with a row for each pair of branches and chosen columns,
+/// or columns of the style when none are chosen
+pub(crate) fn render(
+    results: &[MergeAnalysisResult],
+    columns: &[Column],
+    style: TabularStyle,
+) -> String {
+    let columns = if columns.is_empty() {
+        style.default_columns()
+    } else {
+        columns
+    };
+    let rows = results.iter().map(|result| {
+        columns
+            .iter()
+            .map(|column| value(result, *column, style.is_delimited()))
+            .collect::<Vec<_>>()
+    });
+    let header = columns.iter().map(|column| column.to_string());
+
+    match style {
+        TabularStyle::Table | TabularStyle::Markdown => {
+            let mut builder = Builder::default();
+            builder.set_header(header);
+            for row in rows {
+                builder.push_record(row);
+            }
+            let mut table = builder.build();
+            if style == TabularStyle::Markdown {
+                table.with(tabled::settings::Style::markdown());
+            }
+            format!("{}\n", table)
+        }
+        TabularStyle::Csv => delimited(header, rows, ",", csv_field),
+        TabularStyle::Tsv => delimited(header, rows, "\t", tsv_field),
+    }
+}
+
+fn value(result: &MergeAnalysisResult, column: Column, plain: bool) -> String {
+    let count = |count: Option<usize>| count.map(|count| count.to_string()).unwrap_or_default();
+    match column {
+        Column::FromBranch if plain => result.from_branch.clone(),
+        Column::FromBranch => result.display_from_branch(),
+        Column::ToBranch if plain => result.to_branch.clone(),
+        Column::ToBranch => result.display_to_branch(),
+        Column::Status => result.status.to_string(),
+        Column::StatusCode => result.status.code().to_owned(),
+        Column::FromCommit => result.from_commit.clone().unwrap_or_default(),
+        Column::ToCommit => result.to_commit.clone().unwrap_or_default(),
+        Column::Ahead => count(result.ahead),
+        Column::Behind => count(result.behind),
+        Column::Conflicts => result.conflicts.len().to_string(),
+    }
+}
+
+fn delimited(
+    header: impl Iterator<Item = String>,
+    rows: impl Iterator<Item = Vec<String>>,
+    separator: &str,
+    field: fn(&str) -> String,
+) -> String {
+    std::iter::once(header.collect::<Vec<_>>())
+        .chain(rows)
+        .map(|row| {
+            let fields = row.iter().map(|value| field(value)).collect::<Vec<_>>();
+            format!("{}\n", fields.join(separator))
+        })
+        .collect()
+}
+
+/// Quotes field that contains separator, quotes or line breaks, as RFC 4180 does
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Tab separated values cannot be quoted, so tabs and line breaks are replaced
+fn tsv_field(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::MergeAnalysisStatus;
+    use pretty_assertions::assert_eq;
+
+    fn results() -> Vec<MergeAnalysisResult> {
+        vec![
+            MergeAnalysisResult {
+                from_branch: "feature".to_string(),
+                to_branch: "main".to_string(),
+                status: MergeAnalysisStatus::Conflicts,
+                to_default: true,
+                from_commit: Some("1111111".to_string()),
+                to_commit: Some("2222222".to_string()),
+                ahead: Some(2),
+                behind: Some(1),
+                conflicts: vec!["src/lib.rs".to_string()],
+                ..Default::default()
+            },
+            MergeAnalysisResult {
+                from_branch: "main".to_string(),
+                to_branch: "feature".to_string(),
+                status: MergeAnalysisStatus::Error {
+                    message: "no merge base, \"really\"".to_string(),
+                },
+                from_default: true,
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_render_csv() {
+        assert_eq!(
+            render(&results(), &[], TabularStyle::Csv),
+            r#"from_branch,to_branch,status_code,status
+feature,main,conflicts,"🚧🔧 Found conflicts, have to resolve them manually."
+main,feature,error,"❌❌ No merge is possible - no merge base, ""really""."
+"#
+        );
+    }
+
+    #[test]
+    fn test_render_columns() {
+        let columns = [
+            Column::FromBranch,
+            Column::ToBranch,
+            Column::FromCommit,
+            Column::ToCommit,
+            Column::Ahead,
+            Column::Behind,
+            Column::Conflicts,
+        ];
+        assert_eq!(
+            render(&results(), &columns, TabularStyle::Tsv),
+            "from_branch\tto_branch\tfrom_commit\tto_commit\tahead\tbehind\tconflicts\n\
+             feature\tmain\t1111111\t2222222\t2\t1\t1\n\
+             main\tfeature\t\t\t\t\t0\n"
+        );
+        assert_eq!(
+            render(
+                &results(),
+                &[Column::ToBranch, Column::StatusCode],
+                TabularStyle::Markdown
+            ),
+            r#"
+| to_branch      | status_code |
+|----------------|-------------|
+| main (default) | conflicts   |
+| feature        | error       |
+"#
+            .trim_start()
+        );
+    }
+}
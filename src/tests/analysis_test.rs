@@ -1,15 +1,60 @@
+use crate::analysis::{AnalysisOptions, AnalysisSink, WORKING_TREE};
+use crate::cache::cache_root;
+use crate::cli::{BranchKind, CloneStrategy};
 use crate::interactive::{Analyzer as _, Repo};
-use crate::result::MergeAnalysisStatus;
+use crate::recent::{BranchSource, Selection};
+use crate::repo::RepoOptions;
+use crate::result::{MergeAnalysisResult, MergeAnalysisStatus};
 use crate::tests::support::git::{
-    assert_result, create_bare_repo, create_branch, create_branch_with_commit, create_commit,
-    create_repo,
+    assert_result, clone_repo, create_bare_repo, create_branch, create_branch_with_commit,
+    create_commit, create_repo,
 };
 use crate::Probranchinator;
-use std::env;
 use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
+fn recent(recent: usize) -> Selection {
+    Selection {
+        recent,
+        ..Default::default()
+    }
+}
+
+/// Records what analysis passed on while it was running
+#[derive(Default)]
+struct RecordingSink {
+    events: Vec<String>,
+}
+
+impl AnalysisSink for RecordingSink {
+    fn started(&mut self, pairs: usize) -> eyre::Result<()> {
+        self.events.push(format!("started {}", pairs));
+        Ok(())
+    }
+
+    fn analysing(&mut self, from_branch: &str, into_branch: &str) -> eyre::Result<()> {
+        self.events
+            .push(format!("analysing {} -> {}", from_branch, into_branch));
+        Ok(())
+    }
+
+    fn analysed(&mut self, result: &MergeAnalysisResult) -> eyre::Result<()> {
+        self.events.push(format!(
+            "analysed {} -> {}: {}",
+            result.from_branch,
+            result.to_branch,
+            result.status.code()
+        ));
+        Ok(())
+    }
+
+    fn finished(&mut self) -> eyre::Result<()> {
+        self.events.push("finished".to_string());
+        Ok(())
+    }
+}
+
 #[test]
 fn test_analysis_one_branch() -> eyre::Result<()> {
     let tested = Probranchinator {};
@@ -25,18 +70,25 @@ fn test_analysis_one_branch() -> eyre::Result<()> {
     // there is going to be one default master branch
 
     // Clone the repository
-    let (cloned_repo, _, _) = tested.get_repo(&remote_url)?;
+    let (cloned_repo, _, _, _) = tested.get_repo(&remote_url, &RepoOptions::default())?;
 
-    // Check that the repository path contains "probranchinator" and is under the system temporary directory
+    // Check that the repository path contains "probranchinator" and is under the cache directory
     assert!(cloned_repo
         .path()
         .to_string_lossy()
         .contains("probranchinator"));
 
-    assert!(cloned_repo.path().starts_with(env::temp_dir()));
+    assert!(cloned_repo.path().starts_with(cache_root(None)));
 
     // Run analysis
-    let result = tested.analyse(cloned_repo, vec![], 2)?;
+    let result = tested.analyse(
+        cloned_repo,
+        vec![],
+        recent(2),
+        BranchSource::Origin,
+        AnalysisOptions::default(),
+        &mut (),
+    )?;
 
     // As there is only one branch, we expect no results, since there is nothing to merge
     assert_eq!(result.len(), 0);
@@ -62,10 +114,17 @@ fn test_analysis_up_to_date_and_fast_forward() -> eyre::Result<()> {
     create_branch_with_commit(&origin, branch_name, "first commit", None)?;
 
     // Clone the repository
-    let (cloned_repo, _, _) = tested.get_repo(&remote_url)?;
+    let (cloned_repo, _, _, _) = tested.get_repo(&remote_url, &RepoOptions::default())?;
 
     // Run analysis with 2 recent branches
-    let result = tested.analyse(cloned_repo, vec![], 2)?;
+    let result = tested.analyse(
+        cloned_repo,
+        vec![],
+        recent(2),
+        BranchSource::Origin,
+        AnalysisOptions::default(),
+        &mut (),
+    )?;
 
     // With two branches we expect two results
     assert_eq!(result.len(), 2);
@@ -89,6 +148,12 @@ fn test_analysis_up_to_date_and_fast_forward() -> eyre::Result<()> {
         MergeAnalysisStatus::FastForward,
     );
 
+    // master is marked as default branch of the repository
+    assert!(result
+        .iter()
+        .all(|r| r.from_default == (r.from_branch == "master")
+            && r.to_default == (r.to_branch == "master")));
+
     Ok(())
 }
 
@@ -110,33 +175,36 @@ fn test_analysis_unrelated_branches() -> eyre::Result<()> {
     create_branch(&origin, branch_name, Some(&commit))?;
 
     // Clone the repository
-    let (cloned_repo, _, _) = tested.get_repo(&remote_url)?;
+    let (cloned_repo, _, _, _) = tested.get_repo(&remote_url, &RepoOptions::default())?;
 
     // Run analysis specifically with the test-branch and master
+    let mut sink = RecordingSink::default();
     let result = tested.analyse(
         cloned_repo,
         vec![branch_name.to_string(), "master".to_string()],
-        0,
+        Selection::default(),
+        BranchSource::Origin,
+        AnalysisOptions::default(),
+        &mut sink,
     )?;
 
-    // Check that master cannot be merged to test-branch and vice versa
-    assert_result(
-        &result,
-        "master",
-        branch_name,
-        MergeAnalysisStatus::Error {
-            message: "no merge base found".to_string(),
-        },
-    );
-    assert_result(
-        &result,
-        branch_name,
-        "master",
-        MergeAnalysisStatus::Error {
-            message: "no merge base found".to_string(),
-        },
+    // each pair is passed on as soon as it is analysed
+    assert_eq!(
+        sink.events,
+        vec![
+            "started 2".to_string(),
+            format!("analysing {} -> master", branch_name),
+            format!("analysed {} -> master: none", branch_name),
+            format!("analysing master -> {}", branch_name),
+            format!("analysed master -> {}: none", branch_name),
+            "finished".to_string(),
+        ]
     );
 
+    // Check that master cannot be merged to test-branch and vice versa
+    assert_result(&result, "master", branch_name, MergeAnalysisStatus::None);
+    assert_result(&result, branch_name, "master", MergeAnalysisStatus::None);
+
     Ok(())
 }
 
@@ -197,7 +265,7 @@ fn test_analysis_normal_merge_no_conflicts() -> eyre::Result<()> {
     create_and_commit_file(&origin, "test2.txt", "test", "test commit", branch_name)?;
 
     // Clone the repository
-    let (cloned_repo, _, _) = tested.get_repo(&remote_url)?;
+    let (cloned_repo, _, _, _) = tested.get_repo(&remote_url, &RepoOptions::default())?;
 
     println!("Cloned repo at {:?}", cloned_repo.path());
 
@@ -205,7 +273,10 @@ fn test_analysis_normal_merge_no_conflicts() -> eyre::Result<()> {
     let result = tested.analyse(
         cloned_repo,
         vec![branch_name.to_string(), "master".to_string()],
-        0,
+        Selection::default(),
+        BranchSource::Origin,
+        AnalysisOptions::default(),
+        &mut (),
     )?;
 
     // Check that master can be normally merged to test-branch and vice versa
@@ -235,7 +306,7 @@ fn test_analysis_normal_merge_with_conflicts() -> eyre::Result<()> {
     create_and_commit_file(&origin, "test.txt", "text 2", "test commit", branch_name)?;
 
     // Clone the repository
-    let (cloned_repo, _, _) = tested.get_repo(&remote_url)?;
+    let (cloned_repo, _, _, _) = tested.get_repo(&remote_url, &RepoOptions::default())?;
 
     println!("Cloned repo at {:?}", cloned_repo.path());
 
@@ -243,7 +314,10 @@ fn test_analysis_normal_merge_with_conflicts() -> eyre::Result<()> {
     let result = tested.analyse(
         cloned_repo,
         vec![branch_name.to_string(), "master".to_string()],
-        0,
+        Selection::default(),
+        BranchSource::Origin,
+        AnalysisOptions::default(),
+        &mut (),
     )?;
 
     // Check that master causes conflicts when merged to test-branch and vice versa
@@ -260,5 +334,424 @@ fn test_analysis_normal_merge_with_conflicts() -> eyre::Result<()> {
         MergeAnalysisStatus::Conflicts,
     );
 
+    // conflicting files and tips of branches are reported too
+    for merge_analysis in &result {
+        assert_eq!(merge_analysis.conflicts, vec!["test.txt".to_string()]);
+        assert!(merge_analysis.from_commit.is_some());
+        assert!(merge_analysis.to_commit.is_some());
+        assert!(merge_analysis
+            .from_committed_at
+            .as_deref()
+            .is_some_and(|time| humantime::parse_rfc3339(time).is_ok()));
+        assert!(merge_analysis.to_committed_at.is_some());
+        assert_eq!(merge_analysis.ahead, Some(1));
+        assert_eq!(merge_analysis.behind, Some(1));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_analysis_working_tree() -> eyre::Result<()> {
+    let tested = Probranchinator {};
+    let (_tmp_dir, origin) = create_repo()?;
+
+    // Initialize first commit in origin repository
+    create_commit(&origin, "initial commit", &[], Some("HEAD"), None)?;
+    create_and_commit_file(&origin, "test.txt", "text 1", "test commit", "master")?;
+
+    // Create a branch in the origin repository changing the same file
+    let branch_name = "test-branch";
+    create_branch(&origin, branch_name, None)?;
+    create_and_commit_file(&origin, "test.txt", "text 2", "test commit", branch_name)?;
+
+    // Clone the repository as user would have it locally
+    let (_clone_dir, local) = clone_repo(&origin)?;
+
+    // Nothing is changed yet, so there is nothing to merge
+    let result = tested.analyse_working_tree(
+        tested.open_repo(local.workdir().unwrap())?,
+        vec![branch_name.to_string(), "master".to_string()],
+        Selection::default(),
+        &mut (),
+    )?;
+    assert_eq!(result.len(), 2);
+    assert_result(
+        &result,
+        WORKING_TREE,
+        branch_name,
+        MergeAnalysisStatus::UpToDate,
+    );
+
+    // Change the file without committing it
+    let file_path = local.workdir().unwrap().join("test.txt");
+    std::fs::write(&file_path, "text 3")?;
+    let index_before = std::fs::read(local.path().join("index"))?;
+
+    let result = tested.analyse_working_tree(
+        tested.open_repo(local.workdir().unwrap())?,
+        vec![branch_name.to_string(), "master".to_string()],
+        Selection::default(),
+        &mut (),
+    )?;
+
+    // Uncommitted change applies cleanly on master, where it was made,
+    // but conflicts with the change done in test-branch
+    assert_result(&result, WORKING_TREE, "master", MergeAnalysisStatus::Normal);
+    assert_result(
+        &result,
+        WORKING_TREE,
+        branch_name,
+        MergeAnalysisStatus::Conflicts,
+    );
+
+    // Working tree and index are left as they were
+    assert_eq!(std::fs::read_to_string(&file_path)?, "text 3");
+    assert_eq!(std::fs::read(local.path().join("index"))?, index_before);
+
+    Ok(())
+}
+
+#[test]
+fn test_analysis_local_repository() -> eyre::Result<()> {
+    let tested = Probranchinator {};
+    let (_tmp_dir, origin) = create_repo()?;
+
+    // Initialize first commit in origin repository
+    create_commit(&origin, "initial commit", &[], Some("HEAD"), None)?;
+    create_and_commit_file(&origin, "test.txt", "text 1", "test commit", "master")?;
+
+    // Clone the repository as user would have it locally
+    let (_clone_dir, local) = clone_repo(&origin)?;
+
+    // Create a branch, which is only known to local repository
+    let branch_name = "unpushed-branch";
+    create_branch(&local, branch_name, None)?;
+    create_and_commit_file(&local, "test.txt", "text 2", "test commit", branch_name)?;
+
+    // Change the file in master of local repository too, without pushing
+    create_and_commit_file(&local, "test.txt", "text 3", "test commit", "master")?;
+
+    let head_before = local.head()?.peel_to_commit()?.id();
+    let index_before = std::fs::read(local.path().join("index"))?;
+    let content_before = std::fs::read_to_string(local.workdir().unwrap().join("test.txt"))?;
+
+    // Run analysis of local branches only
+    let result = tested.analyse(
+        tested.open_repo(local.workdir().unwrap())?,
+        vec![],
+        recent(10),
+        BranchSource::Local(BranchKind::Local),
+        AnalysisOptions::default(),
+        &mut (),
+    )?;
+    assert_eq!(result.len(), 2);
+    assert_result(
+        &result,
+        branch_name,
+        "master",
+        MergeAnalysisStatus::Conflicts,
+    );
+    assert_result(
+        &result,
+        "master",
+        branch_name,
+        MergeAnalysisStatus::Conflicts,
+    );
+
+    // Run analysis of local and remote-tracking branches
+    let result = tested.analyse(
+        tested.open_repo(local.workdir().unwrap())?,
+        vec![],
+        recent(10),
+        BranchSource::Local(BranchKind::All),
+        AnalysisOptions::default(),
+        &mut (),
+    )?;
+
+    // Local master is ahead of one on remote
+    assert_eq!(result.len(), 6);
+    assert_result(
+        &result,
+        "master",
+        "origin/master",
+        MergeAnalysisStatus::FastForward,
+    );
+    assert_result(
+        &result,
+        "origin/master",
+        "master",
+        MergeAnalysisStatus::UpToDate,
+    );
+    assert_result(
+        &result,
+        branch_name,
+        "origin/master",
+        MergeAnalysisStatus::FastForward,
+    );
+
+    // Repository is left as it was
+    assert_eq!(local.head()?.peel_to_commit()?.id(), head_before);
+    assert_eq!(std::fs::read(local.path().join("index"))?, index_before);
+    assert_eq!(
+        std::fs::read_to_string(local.workdir().unwrap().join("test.txt"))?,
+        content_before
+    );
+    assert!(local.state() == git2::RepositoryState::Clean);
+
+    Ok(())
+}
+
+#[test]
+fn test_analysis_partial_clone() -> eyre::Result<()> {
+    let tested = Probranchinator {};
+    let (_tmp_dir, origin) = create_repo()?;
+
+    // partial clones need remote to allow filters and fetching objects by id
+    let mut config = origin.config()?;
+    config.set_bool("uploadpack.allowFilter", true)?;
+    config.set_bool("uploadpack.allowAnySHA1InWant", true)?;
+
+    let remote_url = format!("file:///{}", PathBuf::from(origin.path()).display());
+    println!("Using repo from {:?}", remote_url);
+
+    create_commit(&origin, "initial commit", &[], Some("HEAD"), None)?;
+    create_branch(&origin, "conflicting", None)?;
+    create_branch(&origin, "other", None)?;
+    create_and_commit_file(&origin, "test.txt", "master", "test commit", "master")?;
+    create_and_commit_file(&origin, "other.txt", "other", "other commit", "other")?;
+    create_and_commit_file(
+        &origin,
+        "test.txt",
+        "conflict",
+        "test commit",
+        "conflicting",
+    )?;
+
+    let master_blob = origin
+        .revparse_single("master:test.txt")?
+        .peel_to_blob()?
+        .id();
+
+    for clone in [CloneStrategy::Blobless, CloneStrategy::Treeless] {
+        let cache_dir = tempfile::tempdir()?;
+        let options = RepoOptions {
+            cache_dir: Some(cache_dir.path().to_owned()),
+            clone,
+            ..Default::default()
+        };
+        let (cloned_repo, _, _, _) = tested.get_repo(&remote_url, &options)?;
+
+        // file contents are not cloned, but fetched when merge needs them
+        assert!(crate::partial::is_partial(&cloned_repo), "{}", clone);
+        assert!(!cloned_repo.odb()?.exists(master_blob), "{}", clone);
+
+        let result = tested.analyse(
+            cloned_repo,
+            vec![
+                "master".to_string(),
+                "conflicting".to_string(),
+                "other".to_string(),
+            ],
+            Selection::default(),
+            BranchSource::Origin,
+            AnalysisOptions::default(),
+            &mut (),
+        )?;
+        assert_result(
+            &result,
+            "conflicting",
+            "master",
+            MergeAnalysisStatus::Conflicts,
+        );
+        assert_result(&result, "other", "master", MergeAnalysisStatus::Normal);
+
+        let (cloned_repo, _, _, _) = tested.get_repo(&remote_url, &options)?;
+        assert!(cloned_repo.odb()?.exists(master_blob), "{}", clone);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_analysis_shallow_clone() -> eyre::Result<()> {
+    let tested = Probranchinator {};
+    let (_tmp_dir, origin) = create_repo()?;
+    let remote_url = format!("file:///{}", PathBuf::from(origin.path()).display());
+    println!("Using repo from {:?}", remote_url);
+
+    // merge base of branches is a few commits behind their last commits
+    create_commit(&origin, "initial commit", &[], Some("HEAD"), None)?;
+    create_branch(&origin, "feature", None)?;
+    for i in 0..3 {
+        create_and_commit_file(&origin, "test.txt", &i.to_string(), "test commit", "master")?;
+    }
+    create_and_commit_file(&origin, "other.txt", "other", "other commit", "feature")?;
+
+    // clone with only last commit of each branch
+    let clone_dir = tempfile::tempdir()?;
+    for args in [
+        vec!["init", "--bare", "--quiet"],
+        vec!["remote", "add", "origin", &remote_url],
+        vec!["fetch", "--quiet", "--depth=1", "origin"],
+    ] {
+        let status = std::process::Command::new("git")
+            .args(args)
+            .current_dir(clone_dir.path())
+            .status()?;
+        assert!(status.success());
+    }
+
+    let analyse = |deepen_limit| {
+        let cloned_repo = git2::Repository::open(clone_dir.path())?;
+        assert!(cloned_repo.is_shallow());
+        tested.analyse(
+            cloned_repo,
+            vec!["master".to_string(), "feature".to_string()],
+            Selection::default(),
+            BranchSource::Origin,
+            AnalysisOptions {
+                deepen_limit,
+                ..Default::default()
+            },
+            &mut (),
+        )
+    };
+
+    // without deepening merge base cannot be found
+    let result = analyse(0)?;
+    assert_result(
+        &result,
+        "feature",
+        "master",
+        MergeAnalysisStatus::BeyondShallowDepth,
+    );
+    assert_result(
+        &result,
+        "master",
+        "feature",
+        MergeAnalysisStatus::BeyondShallowDepth,
+    );
+
+    // origin moves on while clone is analysed
+    let master_tip = |repo: &git2::Repository| {
+        repo.find_reference("refs/remotes/origin/master")
+            .and_then(|reference| reference.peel_to_commit())
+            .map(|commit| commit.id())
+    };
+    let fetched_master = master_tip(&git2::Repository::open(clone_dir.path())?)?;
+    create_and_commit_file(&origin, "test.txt", "3", "test commit", "master")?;
+
+    // history is deepened until it is found
+    let result = analyse(AnalysisOptions::default().deepen_limit)?;
+    assert_result(&result, "feature", "master", MergeAnalysisStatus::Normal);
+    assert_result(&result, "master", "feature", MergeAnalysisStatus::Normal);
+
+    // deepening does not move branches, which all pairs are analysed with
+    let cloned_repo = git2::Repository::open(clone_dir.path())?;
+    assert_eq!(master_tip(&cloned_repo)?, fetched_master);
+    assert!(result
+        .iter()
+        .filter(|result| result.to_branch == "master")
+        .all(|result| result.to_commit == Some(fetched_master.to_string())));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_analysis_hanging_fetch() -> eyre::Result<()> {
+    use crate::git::{GitSettings, NetworkError};
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, Instant};
+
+    let tested = Probranchinator {};
+    let (_tmp_dir, origin) = create_repo()?;
+    let mut config = origin.config()?;
+    config.set_bool("uploadpack.allowFilter", true)?;
+    config.set_bool("uploadpack.allowAnySHA1InWant", true)?;
+    let remote_url = format!("file:///{}", PathBuf::from(origin.path()).display());
+
+    create_commit(&origin, "initial commit", &[], Some("HEAD"), None)?;
+    create_branch(&origin, "feature", None)?;
+    for i in 0..3 {
+        create_and_commit_file(&origin, "test.txt", &i.to_string(), "test commit", "master")?;
+    }
+    create_and_commit_file(&origin, "other.txt", "other", "other commit", "feature")?;
+
+    // wrapper hangs on everything that reaches origin after clone is fetched
+    let bin_dir = tempfile::tempdir()?;
+    let log = bin_dir.path().join("git.log");
+    let binary = bin_dir.path().join("git-wrapper");
+    std::fs::write(
+        &binary,
+        format!(
+            "#!/bin/sh
+case \"$*\" in *set-head*|*--deepen*|*--stdin*) echo \"$*\" >> {:?}; exec sleep 10;; esac\nexec git \"$@\"\n",
+            log
+        ),
+    )?;
+    std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755))?;
+    let git = GitSettings {
+        binary: Some(binary),
+        timeout: Some(Duration::from_millis(300)),
+        retries: 1,
+        ..Default::default()
+    };
+    let attempts = |pattern: &str| -> eyre::Result<usize> {
+        let log = std::fs::read_to_string(&log)?;
+        Ok(log.lines().filter(|line| line.contains(pattern)).count())
+    };
+
+    // default branch is only left unknown when it cannot be found in time
+    let cache_dir = tempfile::tempdir()?;
+    let options = RepoOptions {
+        cache_dir: Some(cache_dir.path().to_owned()),
+        clone: CloneStrategy::Treeless,
+        git: git.clone(),
+        ..Default::default()
+    };
+    let started = Instant::now();
+    let (partial_repo, _, _, _) = tested.get_repo(&remote_url, &options)?;
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(attempts("set-head")?, 2);
+
+    let shallow_dir = tempfile::tempdir()?;
+    for args in [
+        vec!["init", "--bare", "--quiet"],
+        vec!["remote", "add", "origin", &remote_url],
+        vec!["fetch", "--quiet", "--depth=1", "origin"],
+    ] {
+        let status = std::process::Command::new("git")
+            .args(args)
+            .current_dir(shallow_dir.path())
+            .status()?;
+        assert!(status.success());
+    }
+    let shallow_repo = git2::Repository::open(shallow_dir.path())?;
+
+    // fetching missing objects and deepening history fail analysis as
+    // network failure, after each of them is retried once
+    for (repo, pattern) in [(partial_repo, "--stdin"), (shallow_repo, "--deepen")] {
+        let started = Instant::now();
+        let error = tested
+            .analyse(
+                repo,
+                vec!["master".to_string(), "feature".to_string()],
+                Selection::default(),
+                BranchSource::Origin,
+                AnalysisOptions {
+                    git: git.clone(),
+                    ..Default::default()
+                },
+                &mut (),
+            )
+            .unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(NetworkError::is_cause_of(&error), "{:?}", error);
+        assert!(format!("{:?}", error).contains("timed out after 300ms"));
+        assert_eq!(attempts(pattern)?, 2);
+    }
+
     Ok(())
 }
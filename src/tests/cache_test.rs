@@ -0,0 +1,173 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::cache::{self, list_entries, read_metadata, write_metadata};
+use crate::cli::{CacheAction, CloneStrategy};
+use crate::clone::clone_repo;
+use crate::git::GitSettings;
+use crate::interactive::Repo;
+use crate::lock::{CacheLock, LockMode};
+use crate::repo::RepoOptions;
+use crate::tests::support::git::{create_bare_repo, create_branch_with_commit, create_commit};
+
+#[test]
+fn test_cache_list_show_prune_purge() -> eyre::Result<()> {
+    let tested = crate::Probranchinator {};
+    let (_tmp_dir, origin) = create_bare_repo()?;
+    let remote_url = format!("file:///{}", PathBuf::from(origin.path()).display());
+    println!("Using bare repo from {:?}", remote_url);
+
+    create_commit(&origin, "initial commit", &[], Some("HEAD"), None)?;
+    create_branch_with_commit(&origin, "test-branch", "first commit", None)?;
+
+    // clones are cached in separate directory to not interfere with other tests
+    let root = tempfile::tempdir()?;
+    let clone_path = cache::cache_path(root.path(), &remote_url);
+    clone_repo(
+        &remote_url,
+        &clone_path,
+        CloneStrategy::Bare,
+        &GitSettings::default(),
+    )?;
+    write_metadata(&clone_path, &remote_url, &RepoOptions::default())?;
+
+    // clones created by older versions have no metadata
+    clone_repo(
+        &remote_url,
+        &root.path().join("legacy"),
+        CloneStrategy::Full,
+        &GitSettings::default(),
+    )?;
+
+    let entries = list_entries(root.path())?;
+    assert_eq!(entries.len(), 2);
+    let entry = entries
+        .iter()
+        .find(|entry| entry.path == clone_path)
+        .unwrap();
+    assert_eq!(entry.metadata.as_ref().unwrap().remote, remote_url);
+    assert_eq!(entry.branches, Some(2));
+    assert!(entry.size > 0);
+
+    let mut buf = Vec::new();
+    cache::run_cache(
+        CacheAction::List,
+        root.path(),
+        &RepoOptions::default(),
+        &mut buf,
+        &tested,
+    )?;
+    let text = String::from_utf8(buf)?;
+    assert!(text.contains(&remote_url));
+    assert!(text.contains("(unknown)"));
+
+    let mut buf = Vec::new();
+    let show = CacheAction::Show {
+        remote: remote_url.clone(),
+    };
+    cache::run_cache(
+        show,
+        root.path(),
+        &RepoOptions::default(),
+        &mut buf,
+        &tested,
+    )?;
+    let text = String::from_utf8(buf)?;
+    assert!(text.contains(&format!("remote: {}", remote_url)));
+    assert!(text.contains("branches: 2"));
+
+    let show = CacheAction::Show {
+        remote: "https://example.com/not-cached.git".to_string(),
+    };
+    assert!(cache::run_cache(
+        show,
+        root.path(),
+        &RepoOptions::default(),
+        &mut Vec::new(),
+        &tested
+    )
+    .is_err());
+
+    // recently fetched clones are kept
+    let prune = || CacheAction::Prune {
+        older_than: Duration::from_secs(60 * 60),
+    };
+    cache::run_cache(
+        prune(),
+        root.path(),
+        &RepoOptions::default(),
+        &mut Vec::new(),
+        &tested,
+    )?;
+    assert_eq!(list_entries(root.path())?.len(), 2);
+
+    // clone which was not fetched for long is removed together with its metadata
+    let mut metadata = read_metadata(&clone_path).unwrap();
+    metadata.fetched_at -= 2 * 60 * 60;
+    std::fs::write(
+        cache::metadata_path(&clone_path),
+        serde_json::to_string(&metadata)?,
+    )?;
+    cache::run_cache(
+        prune(),
+        root.path(),
+        &RepoOptions::default(),
+        &mut Vec::new(),
+        &tested,
+    )?;
+    assert!(!clone_path.exists());
+    assert!(!cache::metadata_path(&clone_path).exists());
+    assert_eq!(list_entries(root.path())?.len(), 1);
+
+    // clone used by another process is not removed
+    let legacy = root.path().join("legacy");
+    let lock = CacheLock::acquire(&legacy, LockMode::Shared, Duration::ZERO)?;
+    let mut buf = Vec::new();
+    cache::run_cache(
+        CacheAction::Purge,
+        root.path(),
+        &RepoOptions::default(),
+        &mut buf,
+        &tested,
+    )?;
+    let text = String::from_utf8(buf)?;
+    assert!(text.contains("it is used by another process"));
+    assert!(text.ends_with("Removed 0 cached repositories\n"));
+    assert!(legacy.exists());
+    drop(lock);
+
+    let mut buf = Vec::new();
+    cache::run_cache(
+        CacheAction::Purge,
+        root.path(),
+        &RepoOptions::default(),
+        &mut buf,
+        &tested,
+    )?;
+    assert_eq!(String::from_utf8(buf)?, "Removed 1 cached repositories\n");
+    assert!(list_entries(root.path())?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_get_repo_writes_metadata() -> eyre::Result<()> {
+    let tested = crate::Probranchinator {};
+    let (_tmp_dir, origin) = create_bare_repo()?;
+    let remote_url = format!("file:///{}", PathBuf::from(origin.path()).display());
+    create_commit(&origin, "initial commit", &[], Some("HEAD"), None)?;
+
+    let (_, path, _, _) = tested.get_repo(&remote_url, &RepoOptions::default())?;
+    let created = read_metadata(&path).unwrap();
+    assert_eq!(created.remote, remote_url);
+    assert_eq!(created.created_at, created.fetched_at);
+
+    // creation time is kept on next fetch
+    std::thread::sleep(Duration::from_secs(1));
+    tested.get_repo(&remote_url, &RepoOptions::default())?;
+    let fetched = read_metadata(&path).unwrap();
+    assert_eq!(fetched.created_at, created.created_at);
+    assert!(fetched.fetched_at > created.fetched_at);
+
+    Ok(())
+}
@@ -1,6 +1,9 @@
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::{interactive::Repo, recent, tests::support};
+use git2::{Commit, Repository, Signature, Time};
+
+use crate::{cli::SortOrder, interactive::Repo, recent, repo::RepoOptions, tests::support};
 
 #[test]
 fn test_recent() -> eyre::Result<()> {
@@ -24,10 +27,17 @@ fn test_recent() -> eyre::Result<()> {
     support::git::create_branch_with_commit(&origin, branch_name, "first commit", None)?;
 
     // Clone the repository
-    let (cloned_repo, _, _) = probrahcninator.get_repo(&remote_url)?;
+    let (cloned_repo, _, _, _) = probrahcninator.get_repo(&remote_url, &RepoOptions::default())?;
 
     // Get recent branches
-    let recent_branches = recent::get_recent_branches(&cloned_repo, 2)?;
+    let recent_branches = recent::get_recent_branches(
+        &cloned_repo,
+        recent::BranchSource::Origin,
+        &recent::Selection {
+            recent: 2,
+            ..Default::default()
+        },
+    )?;
 
     // Check that first branch is the one we just created
     assert_eq!(recent_branches[0], branch_name);
@@ -37,3 +47,290 @@ fn test_recent() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_recent_include_exclude() -> eyre::Result<()> {
+    let probrahcninator = crate::Probranchinator {};
+
+    let (_tmp_dir, origin) = support::git::create_bare_repo()?;
+
+    let remote_url = format!("file:///{}", PathBuf::from(origin.path()).display());
+    println!("Using bare repo from {:?}", remote_url);
+
+    // initialize first commit in origin repository
+    support::git::create_commit(&origin, "initial commit", &[], Some("HEAD"), None)?;
+
+    // Create branches of bots, which are more recent than the rest
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    support::git::create_branch_with_commit(&origin, "feature/1", "first commit", None)?;
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    support::git::create_branch_with_commit(&origin, "renovate/serde", "bump", None)?;
+    support::git::create_branch_with_commit(&origin, "dependabot/cargo/git2", "bump", None)?;
+
+    // Clone the repository
+    let (cloned_repo, _, _, _) = probrahcninator.get_repo(&remote_url, &RepoOptions::default())?;
+
+    let selection = recent::Selection {
+        recent: 2,
+        exclude: vec!["renovate/*".parse()?, "regex:dependabot/.+".parse()?],
+        ..Default::default()
+    };
+
+    // Bots branches are filtered out before taking most recent ones
+    let recent_branches =
+        recent::get_recent_branches(&cloned_repo, recent::BranchSource::Origin, &selection)?;
+    assert_eq!(recent_branches, vec!["feature/1", "master"]);
+
+    // Explicitly listed branches are filtered too
+    let selected = recent::select_branches(
+        &cloned_repo,
+        vec!["renovate/serde".to_string(), "master".to_string()],
+        recent::BranchSource::Origin,
+        &selection,
+    )?;
+    assert_eq!(selected, vec!["master"]);
+
+    // Only included branches are taken if there are any patterns to include
+    let selection = recent::Selection {
+        recent: 10,
+        include: vec!["feature/*".parse()?, "master".parse()?],
+        ..Default::default()
+    };
+    let recent_branches =
+        recent::get_recent_branches(&cloned_repo, recent::BranchSource::Origin, &selection)?;
+    assert_eq!(recent_branches, vec!["feature/1", "master"]);
+
+    Ok(())
+}
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// Creates commit with given author and dates, counted in days before now
+fn create_commit_at<'repo>(
+    repo: &'repo Repository,
+    update_ref: &str,
+    parent: &Commit,
+    author: (&str, &str),
+    authored_days_ago: i64,
+    committed_days_ago: i64,
+) -> eyre::Result<Commit<'repo>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let author_signature = Signature::new(
+        author.0,
+        author.1,
+        &Time::new(now - authored_days_ago * DAY, 0),
+    )?;
+    let committer_signature = Signature::new(
+        author.0,
+        author.1,
+        &Time::new(now - committed_days_ago * DAY, 0),
+    )?;
+    let oid = repo.commit(
+        Some(update_ref),
+        &author_signature,
+        &committer_signature,
+        "commit",
+        &parent.tree()?,
+        &[parent],
+    )?;
+    Ok(repo.find_commit(oid)?)
+}
+
+#[test]
+fn test_recent_sort_and_filters() -> eyre::Result<()> {
+    let probrahcninator = crate::Probranchinator {};
+
+    let (_tmp_dir, origin) = support::git::create_bare_repo()?;
+
+    let remote_url = format!("file:///{}", PathBuf::from(origin.path()).display());
+    println!("Using bare repo from {:?}", remote_url);
+
+    let alice = ("Alice", "alice@example.com");
+    let bob = ("Bob", "bob@example.com");
+
+    // master has two commits, "old" branch is created from the first one
+    let initial = support::git::create_commit(&origin, "initial commit", &[], Some("HEAD"), None)?;
+    let initial = create_commit_at(&origin, "refs/heads/master", &initial, bob, 30, 30)?;
+    create_commit_at(&origin, "refs/heads/old", &initial, alice, 20, 20)?;
+    let master = create_commit_at(&origin, "refs/heads/master", &initial, bob, 5, 5)?;
+
+    // "alice" branch has commit of Alice, but the last one is by Bob
+    let commit = create_commit_at(&origin, "refs/heads/alice", &master, alice, 3, 3)?;
+    create_commit_at(&origin, "refs/heads/alice", &commit, bob, 2, 2)?;
+
+    // "bob" branch was authored long ago, but committed recently
+    create_commit_at(&origin, "refs/heads/bob", &master, bob, 10, 1)?;
+
+    // Clone the repository
+    let (cloned_repo, _, _, _) = probrahcninator.get_repo(&remote_url, &RepoOptions::default())?;
+
+    let recent_branches = |selection: recent::Selection| {
+        recent::get_recent_branches(
+            &cloned_repo,
+            recent::BranchSource::Origin,
+            &recent::Selection {
+                recent: 10,
+                ..selection
+            },
+        )
+        .unwrap()
+    };
+    let sorted = |sort: SortOrder| {
+        recent_branches(recent::Selection {
+            sort,
+            ..Default::default()
+        })
+    };
+
+    assert_eq!(
+        recent::default_branch(&cloned_repo, recent::BranchSource::Origin),
+        Some("master".to_string())
+    );
+
+    assert_eq!(
+        sorted(SortOrder::CommitterDate),
+        vec!["bob", "alice", "master", "old"]
+    );
+    assert_eq!(
+        sorted(SortOrder::AuthorDate),
+        vec!["alice", "master", "bob", "old"]
+    );
+    assert_eq!(
+        sorted(SortOrder::Name),
+        vec!["alice", "bob", "master", "old"]
+    );
+
+    // ties are resolved by committer date
+    assert_eq!(
+        sorted(SortOrder::AheadOfBase),
+        vec!["alice", "bob", "old", "master"]
+    );
+    assert_eq!(
+        sorted(SortOrder::BehindBase),
+        vec!["old", "bob", "alice", "master"]
+    );
+
+    // branches can be compared with another base too
+    assert_eq!(
+        recent_branches(recent::Selection {
+            sort: SortOrder::AheadOfBase,
+            base: Some("bob".to_string()),
+            ..Default::default()
+        }),
+        vec!["alice", "old", "bob", "master"]
+    );
+
+    assert_eq!(
+        recent_branches(recent::Selection {
+            since: Some(Duration::from_secs(7 * DAY as u64)),
+            ..Default::default()
+        }),
+        vec!["bob", "alice", "master"]
+    );
+    assert_eq!(
+        recent_branches(recent::Selection {
+            until: Some(Duration::from_secs(7 * DAY as u64)),
+            ..Default::default()
+        }),
+        vec!["old"]
+    );
+
+    // default branch is always chosen first, even if it does not pass filters
+    assert_eq!(
+        recent_branches(recent::Selection {
+            since: Some(Duration::from_secs(36 * 60 * 60)),
+            default_branch: true,
+            ..Default::default()
+        }),
+        vec!["master", "bob"]
+    );
+    assert_eq!(
+        recent::get_recent_branches(
+            &cloned_repo,
+            recent::BranchSource::Origin,
+            &recent::Selection {
+                recent: 2,
+                default_branch: true,
+                ..Default::default()
+            },
+        )?,
+        vec!["master", "bob"]
+    );
+
+    // Alice has authored last commit of "old" and one of commits of "alice"
+    assert_eq!(
+        recent_branches(recent::Selection {
+            author: Some("alice".to_string()),
+            ..Default::default()
+        }),
+        vec!["alice", "old"]
+    );
+    assert_eq!(
+        recent_branches(recent::Selection {
+            author: Some("BOB@".to_string()),
+            ..Default::default()
+        }),
+        vec!["bob", "alice", "master"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_recent_pull_requests() -> eyre::Result<()> {
+    let probrahcninator = crate::Probranchinator {};
+
+    let (_tmp_dir, origin) = support::git::create_bare_repo()?;
+
+    let remote_url = format!("file:///{}", PathBuf::from(origin.path()).display());
+    println!("Using bare repo from {:?}", remote_url);
+
+    let initial = support::git::create_commit(&origin, "initial commit", &[], Some("HEAD"), None)?;
+    support::git::create_branch_with_commit(&origin, "release", "release", Some(&initial))?;
+    support::git::create_commit(&origin, "pr", &[&initial], Some("refs/pull/1/head"), None)?;
+    support::git::create_commit(
+        &origin,
+        "mr",
+        &[&initial],
+        Some("refs/merge-requests/2/head"),
+        None,
+    )?;
+
+    let (cloned_repo, _, _, _) = probrahcninator.get_repo(
+        &remote_url,
+        &RepoOptions {
+            pull_requests: true,
+            ..Default::default()
+        },
+    )?;
+
+    let selection = recent::Selection {
+        recent: 10,
+        pull_request_bases: vec!["2=release".parse().unwrap()],
+        ..Default::default()
+    };
+
+    // base branches are added for pull requests listed explicitly
+    let selected = recent::select_branches(
+        &cloned_repo,
+        vec!["pr/1".to_string(), "pr/2".to_string()],
+        recent::BranchSource::Origin,
+        &selection,
+    )?;
+    assert_eq!(selected, vec!["pr/1", "pr/2", "master", "release"]);
+
+    // and for ones chosen among recent branches
+    let selected = recent::select_branches(
+        &cloned_repo,
+        vec![],
+        recent::BranchSource::Origin,
+        &recent::Selection {
+            include: vec!["pr/*".parse()?],
+            sort: SortOrder::Name,
+            ..selection
+        },
+    )?;
+    assert_eq!(selected, vec!["pr/1", "pr/2", "master", "release"]);
+
+    Ok(())
+}
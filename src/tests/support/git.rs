@@ -19,6 +19,16 @@ pub(crate) fn create_repo() -> Result<(TempDir, Repository), git2::Error> {
     Ok((tmp_dir, repo))
 }
 
+pub(crate) fn clone_repo(origin: &Repository) -> Result<(TempDir, Repository), git2::Error> {
+    println!("Cloning repository for testing in system temporary");
+    let tmp_dir = tempdir().map_err(|e| git2::Error::from_str(&format!("{}", e)))?;
+    let repo_path = tmp_dir.path().join("my-clone");
+    let url = origin.path().to_string_lossy();
+    let repo = Repository::clone(&url, &repo_path)?;
+    println!("Repo cloned to {:?}", repo_path);
+    Ok((tmp_dir, repo))
+}
+
 pub(crate) fn create_commit<'repo>(
     repo: &'repo Repository,
     message: &str,
@@ -70,7 +80,7 @@ pub(crate) fn create_branch<'repo>(
 }
 
 pub(crate) fn assert_result(
-    result: &Vec<crate::result::MergeAnalysisResult>,
+    result: &[crate::result::MergeAnalysisResult],
     from_branch: &str,
     to_branch: &str,
     expected_status: crate::result::MergeAnalysisStatus,